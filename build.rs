@@ -1,5 +1,3 @@
-use cc::Build;
-
 fn main() {
     #[cfg(windows)]
     {
@@ -8,42 +6,100 @@ fn main() {
         res.compile().expect("Failed to embedd icon !");
     }
 
-    Build::new()
-        .file("lua/lapi.c")
-        .file("lua/lauxlib.c")
-        .file("lua/lbaselib.c")
-        .file("lua/lcode.c")
-        .file("lua/lcorolib.c")
-        .file("lua/lctype.c")
-        .file("lua/ldblib.c")
-        .file("lua/ldebug.c")
-        .file("lua/ldo.c")
-        .file("lua/ldump.c")
-        .file("lua/lfunc.c")
-        .file("lua/lgc.c")
-        .file("lua/linit.c")
-        .file("lua/liolib.c")
-        .file("lua/llex.c")
-        .file("lua/lmathlib.c")
-        .file("lua/lmem.c")
-        .file("lua/loadlib.c")
-        .file("lua/lobject.c")
-        .file("lua/lopcodes.c")
-        .file("lua/loslib.c")
-        .file("lua/lparser.c")
-        .file("lua/lstate.c")
-        .file("lua/lstring.c")
-        .file("lua/lstrlib.c")
-        .file("lua/ltable.c")
-        .file("lua/ltablib.c")
-        .file("lua/ltm.c")
-        .file("lua/lundump.c")
-        .file("lua/lutf8lib.c")
-        .file("lua/lvm.c")
-        .file("lua/lzio.c")
-        .file("src/st.c")
-        .include("lua")
-        .compile("lua");
+    // Plugins and launch hooks
+    #[cfg(feature = "lua")]
+    lua::build();
 
     println!("cargo:rerun-if-changed=src/st.c")
 }
+
+#[cfg(feature = "lua")]
+mod lua {
+    /// pkg-config names of Lua 5.4, they differ between distributions.
+    #[cfg(all(feature = "lua-system", not(feature = "luajit")))]
+    const LUA54: [&str; 4] = ["lua5.4", "lua-5.4", "lua54", "lua"];
+
+    #[cfg(any(feature = "lua-system", feature = "luajit"))]
+    fn probe(names: &[&str], version: &str, emit: bool) -> pkg_config::Library {
+        let mut errors = Vec::new();
+        for name in names {
+            match pkg_config::Config::new()
+                .atleast_version(version)
+                .cargo_metadata(emit)
+                .probe(name)
+            {
+                Ok(lib) => return lib,
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        panic!(
+            "No system Lua found with pkg-config, tried {}:\n{}",
+            names.join(", "),
+            errors.join("\n")
+        );
+    }
+
+    /// Only our glue code, Lua itself comes from the system. The library is
+    /// linked after `st` so `st` finds its symbols.
+    #[cfg(any(feature = "lua-system", feature = "luajit"))]
+    fn system(names: &[&str], version: &str, define: Option<&str>) {
+        let lib = probe(names, version, false);
+        let mut build = cc::Build::new();
+        build.file("src/st.c").includes(&lib.include_paths);
+        if let Some(define) = define {
+            build.define(define, None);
+        }
+        build.compile("st");
+        probe(names, version, true);
+    }
+
+    #[cfg(feature = "luajit")]
+    pub fn build() {
+        system(&["luajit"], "2.0", Some("ST_LUAJIT"));
+    }
+
+    #[cfg(all(feature = "lua-system", not(feature = "luajit")))]
+    pub fn build() {
+        system(&LUA54, "5.4", None);
+    }
+
+    #[cfg(not(any(feature = "lua-system", feature = "luajit")))]
+    pub fn build() {
+        cc::Build::new()
+            .file("lua/lapi.c")
+            .file("lua/lauxlib.c")
+            .file("lua/lbaselib.c")
+            .file("lua/lcode.c")
+            .file("lua/lcorolib.c")
+            .file("lua/lctype.c")
+            .file("lua/ldblib.c")
+            .file("lua/ldebug.c")
+            .file("lua/ldo.c")
+            .file("lua/ldump.c")
+            .file("lua/lfunc.c")
+            .file("lua/lgc.c")
+            .file("lua/linit.c")
+            .file("lua/liolib.c")
+            .file("lua/llex.c")
+            .file("lua/lmathlib.c")
+            .file("lua/lmem.c")
+            .file("lua/loadlib.c")
+            .file("lua/lobject.c")
+            .file("lua/lopcodes.c")
+            .file("lua/loslib.c")
+            .file("lua/lparser.c")
+            .file("lua/lstate.c")
+            .file("lua/lstring.c")
+            .file("lua/lstrlib.c")
+            .file("lua/ltable.c")
+            .file("lua/ltablib.c")
+            .file("lua/ltm.c")
+            .file("lua/lundump.c")
+            .file("lua/lutf8lib.c")
+            .file("lua/lvm.c")
+            .file("lua/lzio.c")
+            .file("src/st.c")
+            .include("lua")
+            .compile("lua");
+    }
+}
@@ -3,4 +3,17 @@
 ---Downloads images, text files from the internet using url and output. 
 ---@param url string
 ---@param out string
-download = function(url, out) end
\ No newline at end of file
+download = function(url, out) end
+
+---@class Engine
+---@field name string e.g. "Lua 5.4.7" or "LuaJIT 2.1.1720049189"
+---@field version integer 504 for Lua 5.4, 501 for LuaJIT
+---@field jit boolean
+---@field integers boolean Has a separate integer type
+---@field utf8 boolean Has the utf8 library
+---@field bitops boolean Has the operators & | ~ << >>
+---@field ffi boolean Has LuaJIT's ffi library
+
+---The Lua runtime Steamtools was built with, check it before using features not every runtime has.
+---@type Engine
+engine = {}
@@ -1,11 +1,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
 use std::fmt::Write;
-use std::fs::File;
 use std::path::Path;
-use std::process;
+use std::time::Duration;
 use std::{
     fs,
     path::PathBuf,
@@ -19,14 +17,27 @@ use eframe::egui::{
 use egui_extras::install_image_loaders;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
-use serde::{Deserialize, Serialize};
-use steamtools::{Game, Steam, get_games};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use steamtools::{
+    Game, Steam, atomic,
+    client::SteamClient,
+    collections::{Collection, get_collections},
+    db::{LibraryDb, SharedLibrary},
+    get_games,
+    migrate::{self, MIGRATIONS, Report},
+    paths,
+    sessions::{SessionTracker, running_games, running_shortcuts},
+    uri::SteamUri,
+    userdata,
+};
 
 mod window;
-use window::{InstallPopup, ModsPopup, Plugins, Settings, ViewPopup};
+use window::{
+    ChangesPopup, CleanupPopup, InstallPopup, LaunchOptionsPopup, MigrationPopup, ModsPopup,
+    Plugins, Settings, TransferPopup, ViewPopup,
+};
 
 mod utils;
-use utils::bserializer::GameMap;
 use utils::filter::Filter;
 
 use crate::window::WindowPopup;
@@ -40,18 +51,28 @@ enum State {
 }
 
 const HOOK_DLL: &[u8] = include_bytes!("../deps/xinput1_4.dll");
-const STEAM_BINARY_PATH: &str = "steam.bin";
+const SESSION_POLL: Duration = Duration::from_secs(5);
+/// Keys written by [`App::save`].
+const STORAGE_KEYS: [&str; 6] = ["version", "state", "steam", "settings", "unlock", "changes"];
 
 #[derive(Default)]
 struct App {
     st: Steam,
     settings: Settings,
     state: State,
-    games: Arc<Mutex<HashMap<u32, Game>>>,
-    cached_games: GameMap,
+    games: Arc<SharedLibrary>,
+    /// Appids of the central panel, filtered and sorted by name.
+    shown: Vec<u32>,
+    /// Library generation and filter `shown` was built for.
+    shown_for: Option<(u64, Filter)>,
     loaded: bool,
     view: ViewPopup,
     install: InstallPopup,
+    launch_options: LaunchOptionsPopup,
+    cleanup: CleanupPopup,
+    changes: ChangesPopup,
+    migration: MigrationPopup,
+    transfer: TransferPopup,
     mods: ModsPopup,
     plugins: Plugins,
     unlock: bool,
@@ -59,6 +80,8 @@ struct App {
     buffer: String,
     searchbar: RefCell<String>,
     filter: Filter,
+    collections: Vec<Collection>,
+    sessions: Arc<Mutex<SessionTracker>>,
     selected_game: Cell<u32>,
     delete_request: Option<u32>,
     // settings: Settings,
@@ -66,6 +89,21 @@ struct App {
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Parses a stored value, values that don't fit the current types are
+/// dropped and reported instead.
+fn load<T: DeserializeOwned>(state: &migrate::State, key: &str, report: &mut Report) -> Option<T> {
+    let value = state.get(key)?;
+    match serde_json::from_str(value) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            report
+                .cleared
+                .push(format!("Stored {key}, it was reset ({e})"));
+            None
+        }
+    }
+}
+
 impl App {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
@@ -92,61 +130,72 @@ impl App {
         };
 
         if let Some(storage_ref) = cc.storage {
-            storage_ref.get_string("version" ).map(|version| {
-                app.version = serde_json::from_str::<String>(&version).unwrap();
-                if VERSION != &app.version && let Some(dir) = eframe::storage_dir("steamtools") {
-                    fs::remove_dir_all(dir).unwrap();
-                    fs::remove_dir_all("icons").ok();
-                    #[cfg(not(target_os = "windows"))]
-                    rfd::MessageDialog::new()
-                        .set_title("Info")
-                        .set_buttons(rfd::MessageButtons::Ok)
-                        .set_description("Restart Steamtools !")
-                        .show();
-                    #[cfg(target_os = "windows")] {
-                        app.state = State::Setup;
-                        rfd::MessageDialog::new()
-                            .set_title("Info")
-                            .set_buttons(rfd::MessageButtons::Ok)
-                            .set_description(&format!("Updated to version: {VERSION}\nPlease setup the path for steam again !"))
-                            .show();
-                    }
-                    // exit(0);
-                } else {
-                    storage_ref.get_string("state" ).map(|state| {
-                        app.state = serde_json::from_str::<State>(&state).unwrap();
-                    });
-                }
-            });
+            let mut state: migrate::State = STORAGE_KEYS
+                .iter()
+                .filter_map(|key| Some((key.to_string(), storage_ref.get_string(key)?)))
+                .collect();
+
+            app.version = state
+                .get("version")
+                .and_then(|version| serde_json::from_str::<String>(version).ok())
+                .unwrap_or_default();
+            let mut report = migrate::migrate(&mut state, &app.version, VERSION, MIGRATIONS);
+
+            if let Some(saved) = load(&state, "state", &mut report) {
+                app.state = saved;
+            }
 
-            storage_ref.get_string("steam").map(|s| {
+            if let Some(st) = load::<Steam>(&state, "steam", &mut report) {
                 let mut p = PathBuf::new();
-                app.st = serde_json::from_str::<Steam>(&s).unwrap();
+                app.st = st;
+                // Relative in portable mode
+                app.st.path = paths::load_path(Path::new(&app.st.path))
+                    .to_string_lossy()
+                    .to_string();
+                app.st.cfg = paths::load_path(&app.st.cfg);
                 if app.st.cfg.to_string_lossy().is_empty() {
                     p.push(&app.st.path);
                     p.push("config");
                     p.push("stplug-in");
                     app.st.cfg = p;
                 }
-            });
+            }
 
-            storage_ref.get_string("games").map(|games| {
-                app.games = serde_json::from_str::<Arc<Mutex<HashMap<u32, Game>>>>(&games).unwrap();
-                app.loaded = true;
-            });
+            if let Some(settings) = load(&state, "settings", &mut report) {
+                app.settings = settings;
+            }
 
-            storage_ref.get_string("settings").map(|settings| {
-                app.settings = serde_json::from_str(&settings).unwrap();
-            });
+            if let Some(unlock) = load(&state, "unlock", &mut report) {
+                app.unlock = unlock;
+            }
 
-            storage_ref.get_string("unlock").map(|unlock| {
-                app.unlock = serde_json::from_str(&unlock).unwrap();
-            });
+            if let Some(history) = load(&state, "changes", &mut report) {
+                app.changes.history = history;
+            }
+
+            if !report.is_empty() {
+                info!("{report}");
+                app.migration.report = Some(report);
+            }
         }
 
-        if app.cached_games.0.is_empty() && Path::new(STEAM_BINARY_PATH).exists() {
-            let mut file = File::open(STEAM_BINARY_PATH).unwrap();
-            app.cached_games.0 = GameMap::read_from(&mut file).unwrap();
+        // After migrating, older versions kept the games in the storage
+        let library_path = paths::library_path();
+        match LibraryDb::open(&library_path) {
+            Ok(db) => {
+                app.loaded = !db.is_empty();
+                app.games = Arc::new(SharedLibrary::new(db));
+            }
+            Err(e) => {
+                // Don't overwrite a library we can't read
+                rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Error)
+                    .set_title("Error")
+                    .set_buttons(rfd::MessageButtons::Ok)
+                    .set_description(format!("Opening {}: {e}", library_path.display()))
+                    .show();
+                std::process::exit(1);
+            }
         }
 
         #[cfg(target_os = "windows")]
@@ -158,6 +207,33 @@ impl App {
                 .unwrap();
         }
 
+        let sessions_path = paths::sessions_path();
+        match SessionTracker::load(&sessions_path) {
+            Ok(tracker) => *app.sessions.lock().unwrap() = tracker,
+            Err(e) => error!("Loading {}: {e}", sessions_path.display()),
+        }
+
+        // Watches the running processes for play sessions
+        let games = app.games.clone();
+        let sessions = app.sessions.clone();
+        let steam_path = app.st.path.clone();
+        let ctx = cc.egui_ctx.clone();
+        thread::spawn(move || {
+            loop {
+                let mut running = running_games(games.snapshot().games());
+                running.extend(running_shortcuts(&userdata::shortcuts(&steam_path)));
+
+                let mut tracker = sessions.lock().unwrap();
+                if running != tracker.running() {
+                    ctx.request_repaint();
+                }
+                tracker.update(&running);
+                drop(tracker);
+
+                thread::sleep(SESSION_POLL);
+            }
+        });
+
         app.version = VERSION.to_string();
         app
     }
@@ -165,19 +241,33 @@ impl App {
 
 impl eframe::App for App {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        storage.set_string("steam", serde_json::to_string(&self.st).unwrap());
+        let steam = Steam {
+            path: paths::store_path(Path::new(&self.st.path))
+                .to_string_lossy()
+                .to_string(),
+            cfg: paths::store_path(&self.st.cfg),
+            mod_id: self.st.mod_id.clone(),
+            melon_loader: self.st.melon_loader,
+        };
+        storage.set_string("steam", serde_json::to_string(&steam).unwrap());
         storage.set_string("version", serde_json::to_string(VERSION).unwrap());
         if self.state != State::Setup {
             storage.set_string("state", serde_json::to_string(&State::MainMenu).unwrap());
         } else {
             storage.set_string("state", serde_json::to_string(&self.state).unwrap());
         }
-        storage.set_string("games", serde_json::to_string(&self.games).unwrap());
         storage.set_string("settings", serde_json::to_string(&self.settings).unwrap());
         storage.set_string("unlock", serde_json::to_string(&self.unlock).unwrap());
+        storage.set_string(
+            "changes",
+            serde_json::to_string(&self.changes.history).unwrap(),
+        );
     }
 
     fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        MigrationPopup::view(self, ui);
+        TransferPopup::view(self, ui);
+
         match self.state {
             State::Setup => {
                 egui::CentralPanel::default().show_inside(ui, |ui| {
@@ -257,27 +347,56 @@ impl eframe::App for App {
 
                     ui.add_space(5.0);
 
+                    ui.vertical(|ui| {
+                        ui.label(RichText::new("Backup").font(FontId::proportional(20.0)));
+                        ui.checkbox(&mut self.transfer.artwork, "Include artwork");
+                        ui.horizontal(|ui| {
+                            if ui.button("Export...").on_hover_text("Settings, plugins and mods in one archive, without the Steam path").clicked() {
+                                TransferPopup::export(self);
+                            }
+                            if ui.button("Import...").on_hover_text("Shows what changes before applying").clicked() {
+                                self.transfer.import();
+                            }
+                        });
+                    });
+
+                    ui.add_space(5.0);
+
                     ui.vertical(|ui| {
                         ui.label(RichText::new("Steam").font(FontId::proportional(20.0)));
                         ui.horizontal(|ui| {
-                            ui.vertical(|ui| {
-                                if ui.button("Start").clicked() {
-                                    #[cfg(target_os = "windows")]
-                                    std::process::Command::new("cmd")
-                                        .args(["/C", "start steam://rungameid/0"])
-                                        .spawn()
-                                        .unwrap();
-                                }
-                            });
-                            ui.vertical(|ui| {
-                                if ui.button("Close").clicked() {
-                                    #[cfg(target_os = "windows")]
-                                    std::process::Command::new("cmd")
-                                        .args(["/C", "taskkill /f /im steam.exe"])
-                                        .spawn()
-                                        .unwrap();
-                                }
-                            });
+                            let client = SteamClient::new(&self.st.path);
+                            let start = ui.button("Start").clicked();
+                            let stop = ui.button("Close").on_hover_text("Asks Steam to shut down").clicked();
+                            let kill = ui.button("Force close").on_hover_text("Kills Steam, unsaved changes are lost").clicked();
+                            let restart = ui.button("Restart").clicked();
+
+                            let result = if start {
+                                client.start()
+                            } else if stop {
+                                client.stop()
+                            } else if kill {
+                                client.kill()
+                            } else {
+                                Ok(())
+                            };
+
+                            if restart {
+                                thread::spawn(move || {
+                                    if let Err(e) = client.restart() {
+                                        error!("Restarting Steam: {e}");
+                                    }
+                                });
+                            }
+
+                            if let Err(e) = result {
+                                rfd::MessageDialog::new()
+                                    .set_level(rfd::MessageLevel::Error)
+                                    .set_title("Error")
+                                    .set_description(e.to_string())
+                                    .set_buttons(rfd::MessageButtons::Ok)
+                                    .show();
+                            }
                         });
                     });
                 });
@@ -315,6 +434,10 @@ impl eframe::App for App {
                 Plugins::ceditor(self, ui);
                 ModsPopup::view(self, ui);
                 InstallPopup::view(self, ui);
+                LaunchOptionsPopup::view(self, ui);
+                CleanupPopup::view(self, ui);
+                ChangesPopup::view(self, ui);
+                self.changes.poll();
 
                 egui::Panel::top("top").show_inside(ui, |ui| {
                     ui.vertical(|ui| {
@@ -340,6 +463,14 @@ impl eframe::App for App {
                                     self.install.active = !self.install.active;
                                 }
 
+                                if ui.button("\u{1F9F9} Cleanup").on_hover_text("Find leftover files that can be deleted").clicked() {
+                                    self.cleanup.active = !self.cleanup.active;
+                                }
+
+                                if ui.button("\u{1F558} Changes").on_hover_text("What changed on the last fetches").clicked() {
+                                    self.changes.active = !self.changes.active;
+                                }
+
                                 if ui.button("\u{1F502} Fetch").on_hover_text("Fetch manually in case it doesnt Update the List automatically").clicked() {
                                     self.plugins.fetched = false;
                                     self.loaded = false;
@@ -366,7 +497,7 @@ impl eframe::App for App {
                                         Some(ref files) => {
                                             files.iter().for_each(|file| {
                                                 path.push(&file.file_stem().unwrap());
-                                                fs::copy(file.as_path(),format!("{}.lua", &path.to_string_lossy())).unwrap();
+                                                atomic::copy(file.as_path(),format!("{}.lua", &path.to_string_lossy())).unwrap();
                                             });
                                         },
                                         None => ()
@@ -406,7 +537,7 @@ impl eframe::App for App {
 
                                 if ui.checkbox(&mut self.unlock, "Unlock").changed() {
                                     if self.unlock {
-                                        fs::write(format!("{}\\xinput1_4.dll", self.st.path), HOOK_DLL).unwrap();
+                                        atomic::write(format!("{}\\xinput1_4.dll", self.st.path), HOOK_DLL).unwrap();
                                     } else {
                                         fs::remove_file(format!("{}\\xinput1_4.dll", self.st.path)).unwrap();
                                     }
@@ -453,6 +584,10 @@ impl eframe::App for App {
                     });
                 });
 
+                if self.changes.has_latest() {
+                    egui::Panel::top("changes").show_inside(ui, |ui| self.changes.banner(ui));
+                }
+
                 egui::Panel::right("game_stats")
                     .exact_size(140.0)
                     .show_separator_line(true)
@@ -461,10 +596,8 @@ impl eframe::App for App {
                         ui.vertical_centered(|ui| {
                             let width = ui.available_width();
                             let height = ui.available_height();
-                            let selected_game = self.selected_game.get();
-                            if selected_game != 0 {
-                                let game = self.games.lock().unwrap();
-                                let game = game.get(&selected_game).unwrap();
+                            let snapshot = self.games.snapshot();
+                            if let Some(game) = snapshot.get(self.selected_game.get()) {
                                 ui.label(
                                     RichText::new(&game.details.name)
                                         .font(FontId::new(18.0, egui::FontFamily::Proportional)),
@@ -473,13 +606,15 @@ impl eframe::App for App {
                                 ui.label(&format!("APPID: {}", game.appid));
                                 ui.add_space(8.0);
                                 ui.vertical_centered_justified(|ui| {
+                                    let size = vec2(
+                                        (width * 0.1).clamp(50.0, 70.0),
+                                        (height * 0.1).clamp(25.0, 45.0),
+                                    );
+                                    let mut uri = None;
                                     if game.installed {
                                         if ui
                                             .add_sized(
-                                                vec2(
-                                                    (width * 0.1).clamp(50.0, 70.0),
-                                                    (height * 0.1).clamp(25.0, 45.0),
-                                                ),
+                                                size,
                                                 egui::Button::new(
                                                     RichText::new("\u{1F5D1} Uninstall")
                                                         .strong()
@@ -489,50 +624,66 @@ impl eframe::App for App {
                                             .on_hover_text("Prompts steam to uninstall the game")
                                             .clicked()
                                         {
-                                            self.buffer.clear();
-                                            write!(
-                                                &mut self.buffer,
-                                                "start steam://uninstall/{}",
-                                                game.appid
-                                            )
-                                            .unwrap();
-                                            #[cfg(target_os = "windows")]
-                                            process::Command::new("cmd")
-                                                .args(["/C", &self.buffer])
-                                                .spawn()
-                                                .expect("Failed to uninstall");
-                                            self.buffer.clear();
+                                            uri = Some(SteamUri::Uninstall(game.appid));
                                         }
-                                    } else {
+                                        ui.add_space(2.0);
                                         if ui
-                                            .add_sized(
-                                                vec2(
-                                                    (width * 0.1).clamp(50.0, 70.0),
-                                                    (height * 0.1).clamp(25.0, 45.0),
-                                                ),
-                                                egui::Button::new(
-                                                    RichText::new("\u{2795} Install")
-                                                        .strong()
-                                                        .raised(),
-                                                ),
-                                            )
-                                            .on_hover_text("Prompts steam to install the game")
+                                            .add_sized(size, egui::Button::new("\u{2714} Verify integrity"))
+                                            .on_hover_text("Lets steam check the game files and redownload broken ones")
                                             .clicked()
                                         {
-                                            self.buffer.clear();
-                                            write!(
-                                                &mut self.buffer,
-                                                "start steam://install/{}",
-                                                game.appid
-                                            )
-                                            .unwrap();
-                                            #[cfg(target_os = "windows")]
-                                            process::Command::new("cmd")
-                                                .args(["/C", &self.buffer])
-                                                .spawn()
-                                                .expect("Failed to install");
-                                            self.buffer.clear();
+                                            uri = Some(SteamUri::Validate(game.appid));
                                         }
+                                    } else if ui
+                                        .add_sized(
+                                            size,
+                                            egui::Button::new(
+                                                RichText::new("\u{2795} Install")
+                                                    .strong()
+                                                    .raised(),
+                                            ),
+                                        )
+                                        .on_hover_text("Prompts steam to install the game")
+                                        .clicked()
+                                    {
+                                        uri = Some(SteamUri::Install(game.appid));
+                                    }
+                                    ui.add_space(2.0);
+                                    if ui
+                                        .add_sized(size, egui::Button::new("\u{1F6D2} Store page"))
+                                        .on_hover_text("Opens the store page in steam")
+                                        .clicked()
+                                    {
+                                        uri = Some(SteamUri::StorePage(game.appid));
+                                    }
+                                    ui.add_space(2.0);
+                                    ui.menu_button("\u{2630} More", |ui| {
+                                        let mut actions = vec![
+                                            ("Library page", SteamUri::LibraryPage(game.appid)),
+                                            ("Properties", SteamUri::Properties(game.appid)),
+                                            ("Screenshots", SteamUri::Screenshots(game.appid)),
+                                        ];
+                                        if game.installed {
+                                            actions.insert(0, ("\u{25B6} Play", SteamUri::Run(game.appid)));
+                                            actions.push(("Backup with steam", SteamUri::Backup(game.appid)));
+                                        }
+                                        for (label, action) in actions {
+                                            if ui.button(label).clicked() {
+                                                uri = Some(action);
+                                                ui.close();
+                                            }
+                                        }
+                                    });
+
+                                    if let Some(uri) = uri
+                                        && let Err(e) = uri.open()
+                                    {
+                                        rfd::MessageDialog::new()
+                                            .set_level(rfd::MessageLevel::Error)
+                                            .set_title("Error")
+                                            .set_description(format!("Failed to open {uri}: {e}"))
+                                            .set_buttons(rfd::MessageButtons::Ok)
+                                            .show();
                                     }
                                     ui.add_space(2.0);
                                     if ui
@@ -577,9 +728,7 @@ impl eframe::App for App {
                                                 .set_buttons(rfd::MessageButtons::Ok);
                                         }
 
-                                        p.clear();
-                                        p.push("icons");
-                                        p.push(format!("{}.jpg", &game.appid));
+                                        p = paths::icon_path(game.appid);
                                         debug!("Icon deleted: {}", &p.display());
 
                                         fs::remove_file(&p).ok();
@@ -595,6 +744,37 @@ impl eframe::App for App {
                         });
                     });
 
+                egui::Panel::left("collections")
+                    .default_size(150.0)
+                    .resizable(true)
+                    .show_inside(ui, |ui| {
+                        ui.add_space(5.0);
+                        ui.label(RichText::new("Collections").strong());
+                        ui.separator();
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            if ui.selectable_label(self.filter.is_none(), "All games").clicked() {
+                                self.searchbar.get_mut().clear();
+                                self.filter = Filter::None;
+                            }
+
+                            for collection in &self.collections {
+                                let selected = matches!(&self.filter, Filter::Collection(c) if c.id == collection.id);
+                                let mut label = ui.selectable_label(selected, &collection.name);
+                                if collection.partial {
+                                    label = label.on_hover_text("The filters of this dynamic collection aren't supported, only games added by hand are shown");
+                                }
+                                if label.clicked() {
+                                    self.searchbar.get_mut().clear();
+                                    self.filter = Filter::Collection(collection.clone());
+                                }
+                            }
+
+                            if self.collections.is_empty() {
+                                ui.label("No collections found.");
+                            }
+                        });
+                    });
+
                 egui::CentralPanel::default().show_inside(ui, |ui| {
                     egui::ScrollArea::vertical()
                         .auto_shrink([false; 2])
@@ -602,32 +782,53 @@ impl eframe::App for App {
                             ui.add_space(5.0);
                             ui.horizontal_wrapped(|ui| {
                                 ui.spacing_mut().item_spacing = egui::vec2(12.0, 12.0);
-                                let game_map = { self.games.lock().unwrap().clone() };
+                                let running = self.sessions.lock().unwrap().running();
 
                                 if let Some(s) = self.delete_request.take() {
                                     self.selected_game.set(0);
-                                    self.games.lock().unwrap().remove(&s);
+                                    if let Err(e) = self.games.transaction(|tx| {
+                                        tx.remove(s);
+                                        Ok(())
+                                    }) {
+                                        error!("Removing {s} from the library: {e}");
+                                    }
                                     self.loaded = false;
                                 }
 
-                                for (id, _) in
-                                    game_map.iter().filter(|(gid, g)| match &self.filter {
-                                        Filter::Id(id) => id == *gid,
-                                        Filter::Name(name) => g
-                                            .details
-                                            .name
-                                            .to_lowercase()
-                                            .starts_with(&*name.to_lowercase()),
-                                        Filter::None => true,
-                                    })
+                                // Only rebuilt when the library or the filter changed
+                                let generation = self.games.generation();
+                                if !self
+                                    .shown_for
+                                    .as_ref()
+                                    .is_some_and(|(g, f)| *g == generation && *f == self.filter)
                                 {
+                                    let snapshot = self.games.snapshot();
+                                    let mut shown: Vec<&Game> = snapshot
+                                        .games()
+                                        .values()
+                                        .filter(|g| self.filter.matches(g.appid, g))
+                                        .collect();
+                                    shown.sort_by_cached_key(|g| {
+                                        (g.details.name.to_lowercase(), g.appid)
+                                    });
+                                    self.shown = shown.iter().map(|g| g.appid).collect();
+                                    self.shown_for =
+                                        Some((snapshot.generation, self.filter.clone()));
+                                }
+
+                                for id in &self.shown {
                                     let width = 240.0;
                                     let height = 112.0;
                                     let (card_rect, card_resp) =
                                         ui.allocate_exact_size(vec2(width, height), Sense::hover());
 
                                     self.buffer.clear();
-                                    write!(&mut self.buffer, "file://icons/{}.jpg", id).unwrap();
+                                    write!(
+                                        &mut self.buffer,
+                                        "file://{}",
+                                        paths::icon_path(*id).display()
+                                    )
+                                    .unwrap();
 
                                     ui.scope_builder(UiBuilder::new().max_rect(card_rect), |ui| {
                                         ui.add(
@@ -635,6 +836,22 @@ impl eframe::App for App {
                                                 .corner_radius(egui::CornerRadius::same(6))
                                                 .fit_to_exact_size(egui::vec2(width, height)),
                                         );
+
+                                        if running.contains(id) {
+                                            ui.put(
+                                                egui::Rect::from_min_size(
+                                                    card_rect.min + vec2(6.0, 6.0),
+                                                    vec2(80.0, 20.0),
+                                                ),
+                                                egui::Label::new(
+                                                    RichText::new("\u{25B6} Running")
+                                                        .color(Color32::LIGHT_GREEN)
+                                                        .background_color(
+                                                            Color32::from_black_alpha(180),
+                                                        ),
+                                                ),
+                                            );
+                                        }
                                     });
 
                                     if card_resp.hovered() {
@@ -660,18 +877,41 @@ impl eframe::App for App {
 
                 if !self.loaded {
                     ui.request_repaint();
+                    self.collections = get_collections(&self.st.path);
                     let s = self.st.path.clone();
                     let games_arc = self.games.clone();
+                    let pending = self.changes.pending.clone();
+                    let ctx = ui.ctx().clone();
                     thread::spawn(move || {
-                        let mut sbin = fs::File::create(STEAM_BINARY_PATH).unwrap();
-
-                        let current_games = { games_arc.lock().unwrap().clone() };
-                        GameMap::write_to(&mut sbin, &current_games).unwrap();
+                        let current_games = games_arc.snapshot().games().clone();
 
-                        let result = get_games(&s, current_games);
-
-                        let mut games = games_arc.lock().unwrap();
-                        *games = result;
+                        // Everything is new on the first fetch, not worth a summary
+                        let first = current_games.is_empty();
+                        let (result, changes) = match get_games(&s, current_games.clone()) {
+                            Ok(fetched) => fetched,
+                            Err(e) => {
+                                rfd::MessageDialog::new()
+                                    .set_level(rfd::MessageLevel::Error)
+                                    .set_buttons(rfd::MessageButtons::Ok)
+                                    .set_title("Error")
+                                    .set_description(e.to_string())
+                                    .show();
+                                return;
+                            }
+                        };
+
+                        // Moves and restores may have been committed in the meantime
+                        if let Err(e) = games_arc.transaction(|tx| {
+                            tx.merge(&current_games, result);
+                            Ok(())
+                        }) {
+                            error!("Saving the library: {e}");
+                            return;
+                        }
+                        if !first {
+                            *pending.lock().unwrap() = Some(changes);
+                        }
+                        ctx.request_repaint();
                     });
 
                     self.loaded = true;
@@ -682,6 +922,11 @@ impl eframe::App for App {
 }
 
 fn main() -> eframe::Result<()> {
+    // Before anything resolves a path
+    if std::env::args().skip(1).any(|arg| arg == "--portable") {
+        paths::set_portable();
+    }
+
     #[cfg(not(debug_assertions))]
     {
         use env_logger::Env;
@@ -689,7 +934,7 @@ fn main() -> eframe::Result<()> {
         let log_file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open("steamtools.log")
+            .open(paths::app_log_path())
             .unwrap();
         env_logger::Builder::from_env(
             Env::default().default_filter_or("info,stcli=debug,steamtools=debug,wgpu=off"),
@@ -707,6 +952,7 @@ fn main() -> eframe::Result<()> {
         .init();
     }
 
+    paths::log_dirs();
     info!("GUI: Initializing");
 
     let options = eframe::NativeOptions {
@@ -716,6 +962,8 @@ fn main() -> eframe::Result<()> {
             .with_inner_size([650.0, 370.0])
             .with_min_inner_size([650.0, 370.0])
             .with_icon(eframe::icon_data::from_png_bytes(include_bytes!("../icon.png")).unwrap()),
+        // The app state would end up in the platform folder otherwise
+        persistence_path: paths::is_portable().then(|| paths::config_dir().join("app.ron")),
         ..Default::default()
     };
 
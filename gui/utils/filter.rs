@@ -1,10 +1,13 @@
+use steamtools::{Game, collections::Collection};
+
 /// # Filter
 ///
-/// A filter is used for the searchbar.
+/// A filter is used for the searchbar and the collections sidebar.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub enum Filter {
     Id(u32),
     Name(String),
+    Collection(Collection),
     #[default]
     None,
 }
@@ -24,4 +27,19 @@ impl Filter {
     pub fn is_none(&self) -> bool {
         self == &Filter::None
     }
+
+    /// # matches
+    /// Returns true if the game should be shown !
+    pub fn matches(&self, appid: u32, game: &Game) -> bool {
+        match self {
+            Filter::Id(id) => *id == appid,
+            Filter::Name(name) => game
+                .details
+                .name
+                .to_lowercase()
+                .starts_with(&*name.to_lowercase()),
+            Filter::Collection(collection) => collection.contains(appid, &game.details.name),
+            Filter::None => true,
+        }
+    }
 }
@@ -0,0 +1,112 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use eframe::egui;
+use steamtools::{
+    client::{Applied, SteamClient, WriteOp},
+    steam_running,
+};
+
+/// # Task
+/// An operation started by [`apply`], closing Steam can take a while so it
+/// runs on a thread.
+#[derive(Default)]
+pub enum Task<T> {
+    #[default]
+    Idle,
+    Running,
+    Done(Applied<T>),
+}
+
+pub type SharedTask<T> = Arc<Mutex<Task<T>>>;
+
+/// # confirm
+/// Asks to close Steam if `op` needs it closed and it runs.
+/// Returns false if the user cancelled !
+pub fn confirm(op: WriteOp) -> bool {
+    if !op.needs_steam_closed() || !steam_running() {
+        return true;
+    }
+
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Steam is running")
+        .set_description(format!(
+            "{op} requires Steam to be closed, otherwise Steam overwrites the change.\n\nClose Steam, apply and restart Steam?"
+        ))
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        == rfd::MessageDialogResult::Yes
+}
+
+/// # apply
+/// Runs `f` on a thread after [`confirm`], closing and restarting Steam around
+/// it if needed. The result ends up in `task`, see [`poll`].
+/// Returns false if the user cancelled !
+pub fn apply<T: Send + 'static>(
+    steam_path: &str,
+    op: WriteOp,
+    task: &SharedTask<T>,
+    f: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> bool {
+    if !confirm(op) {
+        return false;
+    }
+
+    *task.lock().unwrap() = Task::Running;
+    let (task, client) = (task.clone(), SteamClient::new(steam_path));
+    thread::spawn(move || {
+        let applied = client.apply(op, f);
+        *task.lock().unwrap() = Task::Done(applied);
+    });
+    true
+}
+
+/// # is_running
+/// True while the task started by [`apply`] has not finished.
+pub fn is_running<T>(task: &SharedTask<T>) -> bool {
+    matches!(*task.lock().unwrap(), Task::Running)
+}
+
+/// # restart_failed
+/// Tells the user that Steam has to be started by hand, the operation itself
+/// is done.
+pub fn restart_failed(e: &io::Error) {
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Steam")
+        .set_description(format!(
+            "The change was applied, but Steam could not be started again: {e}"
+        ))
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+/// # poll
+/// Shows a spinner while the task runs and returns its result once, a failed
+/// restart of Steam is reported on its own.
+pub fn poll<T>(ui: &mut egui::Ui, task: &SharedTask<T>) -> Option<io::Result<T>> {
+    let mut task = task.lock().unwrap();
+    match std::mem::take(&mut *task) {
+        Task::Idle => None,
+        Task::Running => {
+            *task = Task::Running;
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Applying...");
+            });
+            ui.request_repaint();
+            None
+        }
+        Task::Done(applied) => {
+            drop(task);
+            if let Some(e) = &applied.restart {
+                restart_failed(e);
+            }
+            Some(applied.result)
+        }
+    }
+}
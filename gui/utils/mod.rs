@@ -1,3 +1,3 @@
-pub mod bserializer;
 pub mod filter;
+pub mod guard;
 pub mod stack;
@@ -0,0 +1,102 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{App, window::WindowPopup};
+use eframe::egui::{self, RichText, Window};
+use steamtools::{changes::Changes, format_timestamp};
+
+/// Fetches kept in the history.
+const HISTORY_LEN: usize = 50;
+
+/// What changed in the game list on the last fetches.
+#[derive(Default)]
+pub struct ChangesPopup {
+    pub active: bool,
+    /// Set by the fetch thread.
+    pub pending: Arc<Mutex<Option<Changes>>>,
+    /// Shown above the grid until dismissed.
+    latest: Option<Changes>,
+    /// Newest first.
+    pub history: Vec<Changes>,
+}
+
+impl ChangesPopup {
+    /// Takes the changes of a finished fetch into the history.
+    pub fn poll(&mut self) {
+        let Some(changes) = self.pending.lock().unwrap().take() else {
+            return;
+        };
+        if changes.is_empty() {
+            return;
+        }
+        self.history.insert(0, changes.clone());
+        self.history.truncate(HISTORY_LEN);
+        self.latest = Some(changes);
+    }
+
+    pub fn has_latest(&self) -> bool {
+        self.latest.is_some()
+    }
+
+    /// Summary of the last fetch with buttons for the details and to dismiss it.
+    pub fn banner(&mut self, ui: &mut egui::Ui) {
+        let Some(latest) = &self.latest else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("\u{2139} Library changed:").strong());
+            ui.label(latest.summary());
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("\u{2715}").on_hover_text("Dismiss").clicked() {
+                    self.latest = None;
+                }
+                if ui.button("Details").clicked() {
+                    self.active = true;
+                }
+            });
+        });
+    }
+
+    fn entry(ui: &mut egui::Ui, changes: &Changes) {
+        for change in &changes.changes {
+            ui.label(change.to_string());
+        }
+    }
+}
+
+impl WindowPopup for ChangesPopup {
+    fn view(app: &mut App, ui: &mut egui::Ui) {
+        let popup = &mut app.changes;
+        let mut active = popup.active;
+        Window::new("Library changes")
+            .default_size([0.0, 0.0])
+            .open(&mut active)
+            .show(ui, |ui| {
+                if popup.history.is_empty() {
+                    ui.label("No changes recorded yet.");
+                    return;
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for (i, changes) in popup.history.iter().enumerate() {
+                            egui::CollapsingHeader::new(format!(
+                                "{}  {}",
+                                format_timestamp(changes.time),
+                                changes.summary()
+                            ))
+                            .id_salt(("changes", changes.time, i))
+                            .default_open(i == 0)
+                            .show(ui, |ui| Self::entry(ui, changes));
+                        }
+                    });
+
+                ui.separator();
+                if ui.button("\u{1F5D1} Clear history").clicked() {
+                    popup.history.clear();
+                    popup.latest = None;
+                }
+            });
+        app.changes.active = active;
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    App,
+    utils::guard::{self, SharedTask},
+    window::WindowPopup,
+};
+use eframe::egui::{self, RichText, Window};
+use steamtools::{
+    cleanup::{Category, Item, Report, clean, scan, write_op},
+    format_size,
+};
+
+#[derive(Default)]
+pub struct CleanupPopup {
+    pub active: bool,
+    scanning: bool,
+    items: Arc<Mutex<Option<Vec<Item>>>>,
+    /// Indices into `items` marked for deletion.
+    selected: HashSet<usize>,
+    report: Option<String>,
+    task: SharedTask<Report>,
+}
+
+impl CleanupPopup {
+    fn scan(&mut self, app_path: String, games: std::collections::HashMap<u32, steamtools::Game>) {
+        self.scanning = true;
+        self.selected.clear();
+        self.report = None;
+        let items = Arc::new(Mutex::new(None));
+        self.items = items.clone();
+
+        thread::spawn(move || {
+            *items.lock().unwrap() = Some(scan(&app_path, &games));
+        });
+    }
+
+    fn run(&mut self, steam_path: &str, items: &[Item], dry_run: bool) {
+        let chosen: Vec<Item> = self
+            .selected
+            .iter()
+            .filter_map(|i| items.get(*i).cloned())
+            .collect();
+
+        if dry_run {
+            self.finish(clean(&chosen, true), true);
+        } else {
+            guard::apply(steam_path, write_op(&chosen), &self.task, move || {
+                clean(&chosen, false)
+            });
+        }
+    }
+
+    fn finish(&mut self, result: std::io::Result<Report>, dry_run: bool) {
+        match result {
+            Ok(report) => {
+                self.report = Some(report.to_string());
+                if !dry_run {
+                    // Drop what is gone, keep failures for another try
+                    let removed: HashSet<_> = report.removed.iter().map(|i| &i.path).collect();
+                    if let Some(list) = self.items.lock().unwrap().as_mut() {
+                        list.retain(|i| !removed.contains(&i.path));
+                    }
+                    self.selected.clear();
+                }
+            }
+            Err(e) => {
+                rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Error)
+                    .set_title("Error")
+                    .set_description(e.to_string())
+                    .set_buttons(rfd::MessageButtons::Ok)
+                    .show();
+            }
+        }
+    }
+}
+
+impl WindowPopup for CleanupPopup {
+    fn view(app: &mut App, ui: &mut egui::Ui) {
+        let mut active = app.cleanup.active;
+        Window::new("Cleanup")
+            .default_size([0.0, 0.0])
+            .open(&mut active)
+            .show(ui, |ui| {
+                if ui.button("\u{1F50D} Scan").clicked() {
+                    let games = app.games.snapshot().games().clone();
+                    app.cleanup.scan(app.st.path.clone(), games);
+                }
+
+                let popup = &mut app.cleanup;
+                if let Some(result) = guard::poll(ui, &popup.task) {
+                    popup.finish(result, false);
+                }
+                let Some(items) = popup.items.lock().unwrap().clone() else {
+                    if popup.scanning {
+                        ui.spinner();
+                        ui.request_repaint();
+                    }
+                    return;
+                };
+                popup.scanning = false;
+
+                if items.is_empty() {
+                    ui.label("Nothing to clean up.");
+                    return;
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for category in Category::ALL {
+                            let indices: Vec<usize> = (0..items.len())
+                                .filter(|i| items[*i].category == category)
+                                .collect();
+                            if indices.is_empty() {
+                                continue;
+                            }
+
+                            let total = indices.iter().map(|i| items[*i].size).sum();
+                            let mut all = indices.iter().all(|i| popup.selected.contains(i));
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut all, "").changed() {
+                                    for i in &indices {
+                                        if all {
+                                            popup.selected.insert(*i);
+                                        } else {
+                                            popup.selected.remove(i);
+                                        }
+                                    }
+                                }
+                                ui.collapsing(
+                                    RichText::new(format!(
+                                        "{category} ({}, {})",
+                                        indices.len(),
+                                        format_size(total)
+                                    ))
+                                    .strong(),
+                                    |ui| {
+                                        for i in &indices {
+                                            let mut checked = popup.selected.contains(i);
+                                            let label = format!(
+                                                "{} ({})",
+                                                items[*i].path.display(),
+                                                format_size(items[*i].size)
+                                            );
+                                            if ui.checkbox(&mut checked, label).changed() {
+                                                if checked {
+                                                    popup.selected.insert(*i);
+                                                } else {
+                                                    popup.selected.remove(i);
+                                                }
+                                            }
+                                        }
+                                    },
+                                );
+                            });
+                        }
+                    });
+
+                let size: u64 = popup
+                    .selected
+                    .iter()
+                    .filter_map(|i| items.get(*i))
+                    .map(|i| i.size)
+                    .sum();
+                ui.separator();
+                ui.label(format!(
+                    "{} selected, {}",
+                    popup.selected.len(),
+                    format_size(size)
+                ));
+
+                ui.horizontal(|ui| {
+                    let any = !popup.selected.is_empty() && !guard::is_running(&popup.task);
+                    if ui.add_enabled(any, egui::Button::new("Dry run")).clicked() {
+                        popup.run(&app.st.path, &items, true);
+                    }
+                    if ui
+                        .add_enabled(any, egui::Button::new("\u{1F5D1} Delete selected"))
+                        .clicked()
+                        && rfd::MessageDialog::new()
+                            .set_level(rfd::MessageLevel::Warning)
+                            .set_title("Cleanup")
+                            .set_description(format!(
+                                "Delete {} item(s) ({})? This can't be undone.",
+                                popup.selected.len(),
+                                format_size(size)
+                            ))
+                            .set_buttons(rfd::MessageButtons::YesNo)
+                            .show()
+                            == rfd::MessageDialogResult::Yes
+                    {
+                        popup.run(&app.st.path, &items, false);
+                    }
+                });
+
+                if let Some(report) = &popup.report {
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .id_salt("cleanup_report")
+                        .max_height(150.0)
+                        .show(ui, |ui| ui.monospace(report));
+                }
+            });
+        app.cleanup.active = active;
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::App;
+use eframe::egui::{self, Sense, vec2};
+use log::{debug, error};
+use steamtools::{
+    paths,
+    screenshots::{Screenshot, copy_screenshot, delete_screenshot, get_screenshots},
+};
+
+const THUMBNAIL_SIZE: (u32, u32) = (160, 90);
+
+#[derive(Default, Clone)]
+pub enum Thumbnail {
+    #[default]
+    Loading,
+    Ready(PathBuf),
+    /// The image couldn't be decoded, shown as a placeholder.
+    Failed,
+}
+
+#[derive(Default, Clone)]
+pub struct Shot {
+    pub screenshot: Screenshot,
+    pub thumbnail: Thumbnail,
+}
+
+#[derive(Default)]
+pub struct Gallery {
+    appid: u32,
+    /// None while the screenshots are indexed.
+    shots: Arc<Mutex<Option<Vec<Shot>>>>,
+    selected: Option<usize>,
+}
+
+/// Creates (or reuses) a small preview of the screenshot in the thumbnail cache.
+fn thumbnail(shot: &Screenshot, appid: u32) -> Option<PathBuf> {
+    // Every account numbers its screenshots on its own
+    let target = paths::thumbnails_dir()
+        .join(shot.account.to_string())
+        .join(appid.to_string())
+        .join(shot.path.file_name()?);
+    if target.exists() {
+        return Some(target);
+    }
+
+    fs::create_dir_all(target.parent()?).ok()?;
+    // Steams own thumbnails are a lot faster to decode
+    let source = shot.thumbnail.as_ref().unwrap_or(&shot.path);
+    let image = match image::open(source) {
+        Ok(i) => i,
+        Err(e) => {
+            error!("Thumbnail: {}: {e}", source.display());
+            return None;
+        }
+    };
+
+    image
+        .thumbnail(THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1)
+        .save(&target)
+        .ok()?;
+    debug!("Thumbnail created: {}", target.display());
+    Some(target)
+}
+
+impl Gallery {
+    fn load(&mut self, steam_path: String, appid: u32) {
+        self.appid = appid;
+        self.selected = None;
+        let shots = Arc::new(Mutex::new(None));
+        self.shots = shots.clone();
+
+        // Indexing and decoding the images would block the ui
+        thread::spawn(move || {
+            let found = get_screenshots(&steam_path, appid);
+            *shots.lock().unwrap() = Some(
+                found
+                    .iter()
+                    .map(|s| Shot {
+                        screenshot: s.clone(),
+                        thumbnail: Thumbnail::Loading,
+                    })
+                    .collect(),
+            );
+
+            for s in &found {
+                let thumb = thumbnail(s, appid).map_or(Thumbnail::Failed, Thumbnail::Ready);
+                // By path, screenshots can be deleted in the meantime
+                if let Some(shot) = shots
+                    .lock()
+                    .unwrap()
+                    .iter_mut()
+                    .flatten()
+                    .find(|shot| shot.screenshot.path == s.path)
+                {
+                    shot.thumbnail = thumb;
+                }
+            }
+        });
+    }
+
+    pub fn view(app: &mut App, ui: &mut egui::Ui) {
+        if app.view.gallery.appid != app.view.current_game {
+            app.view
+                .gallery
+                .load(app.st.path.clone(), app.view.current_game);
+        }
+
+        let Some(shots) = app.view.gallery.shots.lock().unwrap().clone() else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Looking for screenshots...");
+            });
+            ui.request_repaint();
+            return;
+        };
+        if shots.is_empty() {
+            ui.label("No screenshots found.");
+            if ui.button("\u{1F502} Reload").clicked() {
+                app.view.gallery.appid = 0;
+            }
+            return;
+        }
+
+        if shots
+            .iter()
+            .any(|s| matches!(s.thumbnail, Thumbnail::Loading))
+        {
+            ui.request_repaint();
+        }
+
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (i, shot) in shots.iter().enumerate() {
+                        let size = vec2(THUMBNAIL_SIZE.0 as f32, THUMBNAIL_SIZE.1 as f32);
+                        let resp = match &shot.thumbnail {
+                            Thumbnail::Ready(t) => ui.add(
+                                egui::Image::new(format!("file://{}", t.display()))
+                                    .fit_to_exact_size(size)
+                                    .sense(Sense::click()),
+                            ),
+                            Thumbnail::Loading => ui
+                                .allocate_ui(size, |ui| ui.spinner())
+                                .response
+                                .interact(Sense::click()),
+                            Thumbnail::Failed => ui
+                                .allocate_ui(size, |ui| {
+                                    ui.centered_and_justified(|ui| ui.weak("\u{26A0} No preview"))
+                                })
+                                .response
+                                .interact(Sense::click()),
+                        };
+
+                        if app.view.gallery.selected == Some(i) {
+                            ui.painter().rect_stroke(
+                                resp.rect.expand(2.0),
+                                4.0,
+                                egui::Stroke::new(2.0f32, ui.visuals().selection.stroke.color),
+                                egui::StrokeKind::Middle,
+                            );
+                        }
+
+                        let resp = if shot.screenshot.caption.is_empty() {
+                            resp
+                        } else {
+                            resp.on_hover_text(&shot.screenshot.caption)
+                        };
+                        if resp.clicked() {
+                            app.view.gallery.selected = Some(i);
+                        }
+                    }
+                });
+            });
+
+        let Some(selected) = app.view.gallery.selected.and_then(|i| shots.get(i)) else {
+            return;
+        };
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Open").clicked() {
+                if let Err(e) = steamtools::open(&selected.screenshot.path) {
+                    error!("Open screenshot: {e}");
+                }
+            }
+
+            if ui.button("Copy to folder").clicked()
+                && let Some(dir) = rfd::FileDialog::new().pick_folder()
+                && let Err(e) = copy_screenshot(&selected.screenshot, dir)
+            {
+                rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Error)
+                    .set_title("Error")
+                    .set_description(e.to_string())
+                    .set_buttons(rfd::MessageButtons::Ok)
+                    .show();
+            }
+
+            if ui.button("\u{1F5D1} Delete").clicked()
+                && rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Warning)
+                    .set_title("Delete")
+                    .set_description("Delete this screenshot permanently?")
+                    .set_buttons(rfd::MessageButtons::YesNo)
+                    .show()
+                    == rfd::MessageDialogResult::Yes
+            {
+                match delete_screenshot(&selected.screenshot) {
+                    Ok(()) => {
+                        if let Thumbnail::Ready(t) = &selected.thumbnail {
+                            fs::remove_file(t).ok();
+                        }
+                        if let Some(shots) = app.view.gallery.shots.lock().unwrap().as_mut() {
+                            shots.retain(|s| s.screenshot.path != selected.screenshot.path);
+                        }
+                        app.view.gallery.selected = None;
+                    }
+                    Err(e) => {
+                        rfd::MessageDialog::new()
+                            .set_level(rfd::MessageLevel::Error)
+                            .set_title("Error")
+                            .set_description(e.to_string())
+                            .set_buttons(rfd::MessageButtons::Ok)
+                            .show();
+                    }
+                }
+            }
+        });
+
+        if !selected.screenshot.caption.is_empty() {
+            ui.label(&selected.screenshot.caption);
+        }
+        if selected.screenshot.width != 0 {
+            ui.label(format!(
+                "{}x{}",
+                selected.screenshot.width, selected.screenshot.height
+            ));
+        }
+    }
+}
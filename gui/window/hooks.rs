@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::App;
+use eframe::egui::{self, RichText};
+use steamtools::{
+    launch::{Hooks, launch, load_hooks, log_path, save_hooks},
+    paths,
+};
+
+/// Lines of the game log shown in the tab.
+const LOG_LINES: usize = 40;
+
+#[derive(Default)]
+enum LaunchState {
+    #[default]
+    Idle,
+    Running,
+    Done(Result<(), String>),
+}
+
+/// A launch started from the tab, see [`launch`].
+#[derive(Default)]
+struct Launch {
+    state: Arc<Mutex<LaunchState>>,
+    cancel: Arc<AtomicBool>,
+    /// The log changed since it was read.
+    stale: bool,
+}
+
+/// Lua hooks around launching the game shown in the view popup.
+#[derive(Default)]
+pub struct HooksTab {
+    hooks: Option<HashMap<u32, Hooks>>,
+    appid: Option<u32>,
+    log: String,
+    /// By appid, several games can run at once.
+    launches: HashMap<u32, Launch>,
+}
+
+impl HooksTab {
+    fn load_log(&mut self, appid: u32) {
+        self.appid = Some(appid);
+        let log = fs::read_to_string(log_path(appid)).unwrap_or_default();
+        let lines: Vec<&str> = log.lines().collect();
+        self.log = lines[lines.len().saturating_sub(LOG_LINES)..].join("\n");
+    }
+
+    fn save(&self) {
+        if let Some(hooks) = &self.hooks
+            && let Err(e) = save_hooks(paths::hooks_path(), hooks)
+        {
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Error)
+                .set_title("Error")
+                .set_description(format!("Saving {}: {e}", paths::hooks_path().display()))
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+        }
+    }
+
+    /// Lists the scripts of one phase, returns true if they changed.
+    fn scripts(ui: &mut egui::Ui, title: &str, scripts: &mut Vec<PathBuf>) -> bool {
+        let mut changed = false;
+        ui.label(RichText::new(title).strong());
+
+        let mut remove = None;
+        for (i, script) in scripts.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.button("\u{2715}").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.label(script.display().to_string());
+            });
+        }
+        if let Some(i) = remove {
+            scripts.remove(i);
+            changed = true;
+        }
+
+        if ui.button("\u{2795} Add script...").clicked()
+            && let Some(files) = rfd::FileDialog::new()
+                .add_filter("lua", &["lua"])
+                .set_title(title)
+                .pick_files()
+        {
+            scripts.extend(files);
+            changed = true;
+        }
+        changed
+    }
+
+    pub fn view(app: &mut App, ui: &mut egui::Ui) {
+        let appid = app.view.current_game;
+        let tab = &mut app.view.hooks;
+        if tab.appid != Some(appid) {
+            tab.load_log(appid);
+        }
+        let hooks = tab
+            .hooks
+            .get_or_insert_with(|| load_hooks(paths::hooks_path()))
+            .entry(appid)
+            .or_default();
+
+        let mut changed = Self::scripts(ui, "Before launch", &mut hooks.pre);
+        ui.add_space(4.0);
+        changed |= Self::scripts(ui, "After exit", &mut hooks.post);
+        let hooks = hooks.clone();
+        if changed {
+            tab.save();
+        }
+
+        ui.separator();
+        let mut start = None;
+        let launch_state = tab.launches.entry(appid).or_default();
+        ui.horizontal(|ui| match &*launch_state.state.lock().unwrap() {
+            LaunchState::Running => {
+                ui.spinner();
+                ui.label("Waiting for the game to exit...");
+                if ui
+                    .button("Cancel")
+                    .on_hover_text("Stops the hooks and the waiting, the game keeps running")
+                    .clicked()
+                {
+                    launch_state.cancel.store(true, Ordering::Relaxed);
+                }
+                ui.ctx().request_repaint_after(Duration::from_secs(1));
+            }
+            state => {
+                if let LaunchState::Done(Err(e)) = state {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                let Some(game) = app.games.snapshot().get(appid).cloned() else {
+                    return;
+                };
+                if ui
+                    .add_enabled(game.installed, egui::Button::new("\u{25B6} Launch"))
+                    .on_hover_text("Runs the hooks and starts the game through steam")
+                    .clicked()
+                {
+                    start = Some(game);
+                }
+            }
+        });
+
+        if let Some(game) = start {
+            *launch_state.state.lock().unwrap() = LaunchState::Running;
+            launch_state.cancel.store(false, Ordering::Relaxed);
+            launch_state.stale = true;
+            let (state, cancel) = (launch_state.state.clone(), launch_state.cancel.clone());
+            thread::spawn(move || {
+                let result = launch(&game, &hooks, &cancel).map_err(|e| e.to_string());
+                *state.lock().unwrap() = LaunchState::Done(result);
+            });
+        } else if launch_state.stale
+            && matches!(*launch_state.state.lock().unwrap(), LaunchState::Done(_))
+        {
+            launch_state.stale = false;
+            tab.load_log(appid);
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Log").strong());
+            if ui.button("\u{1F502}").on_hover_text("Reload").clicked() {
+                tab.appid = None;
+            }
+            if ui.button("\u{1F4C2}").on_hover_text("Open log").clicked() {
+                steamtools::open(log_path(appid)).ok();
+            }
+        });
+        egui::ScrollArea::vertical()
+            .id_salt("hooks_log")
+            .max_height(200.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                if tab.log.is_empty() {
+                    ui.label("Nothing logged yet.");
+                } else {
+                    ui.monospace(&tab.log);
+                }
+            });
+    }
+}
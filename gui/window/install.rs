@@ -3,10 +3,10 @@ use eframe::egui::{self, Window};
 use log::debug;
 use std::{
     fmt::Write,
-    fs::File,
-    io::{self, Error, ErrorKind, Write as ioWrite},
+    io::{self, Error, ErrorKind},
     path::{MAIN_SEPARATOR, Path},
 };
+use steamtools::atomic;
 
 const MANIFESTS_URL: &str =
     "https://raw.githubusercontent.com/SteamAutoCracks/ManifestHub/refs/heads";
@@ -46,8 +46,7 @@ fn install(path: &String, appid: i32) -> io::Result<()> {
         ));
     }
 
-    let mut file = File::create_new(sb.as_str())?;
-    file.write_all(&resp.bytes().unwrap())?;
+    atomic::write(sb.as_str(), resp.bytes().unwrap())?;
 
     debug!("Downloaded lua file: {}", sb.as_str());
     rfd::MessageDialog::new()
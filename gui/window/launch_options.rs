@@ -0,0 +1,218 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use crate::{
+    App,
+    utils::guard::{self, SharedTask},
+    window::WindowPopup,
+};
+use eframe::egui::{self, RichText, Window};
+use steamtools::{
+    client::WriteOp,
+    localconfig::{get_launch_options, set_launch_options},
+    userdata::active_user,
+};
+
+/// Launch options of the game shown in the view popup.
+#[derive(Default)]
+pub struct LaunchOptionsTab {
+    appid: Option<u32>,
+    account: Option<u32>,
+    current: String,
+    edit: String,
+    task: SharedTask<(PathBuf, usize)>,
+}
+
+/// Applies the same launch options to many games.
+#[derive(Default)]
+pub struct LaunchOptionsPopup {
+    pub active: bool,
+    pub options: String,
+    pub search: String,
+    pub selected: HashSet<u32>,
+    task: SharedTask<(PathBuf, usize)>,
+}
+
+/// Writes the options on a thread, see [`finished`].
+fn apply(
+    steam_path: &str,
+    account: u32,
+    options: Vec<(u32, String)>,
+    task: &SharedTask<(PathBuf, usize)>,
+) {
+    let path = steam_path.to_string();
+    guard::apply(steam_path, WriteOp::LaunchOptions, task, move || {
+        let options: Vec<(u32, &str)> = options.iter().map(|(id, o)| (*id, o.as_str())).collect();
+        set_launch_options(&path, account, &options).map(|backup| (backup, options.len()))
+    });
+}
+
+/// Tells the user what [`apply`] did once it finished, returns true on success.
+fn finished(ui: &mut egui::Ui, task: &SharedTask<(PathBuf, usize)>) -> bool {
+    match guard::poll(ui, task) {
+        Some(Ok((backup, count))) => {
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Info)
+                .set_title("Launch options")
+                .set_description(format!(
+                    "Launch options saved for {count} game(s).\nBackup: {}",
+                    backup.display()
+                ))
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+            true
+        }
+        Some(Err(e)) => {
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Error)
+                .set_title("Error")
+                .set_description(e.to_string())
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+            false
+        }
+        None => false,
+    }
+}
+
+impl LaunchOptionsTab {
+    pub fn view(app: &mut App, ui: &mut egui::Ui) {
+        let appid = app.view.current_game;
+        let tab = &mut app.view.launch_options;
+        if tab.appid != Some(appid) && !guard::is_running(&tab.task) {
+            tab.appid = Some(appid);
+            tab.account = active_user(&app.st.path);
+            tab.current = tab
+                .account
+                .map(|account| get_launch_options(&app.st.path, account, appid))
+                .unwrap_or_default();
+            tab.edit = tab.current.clone();
+        }
+
+        let Some(account) = tab.account else {
+            ui.label("No Steam account found in userdata.");
+            return;
+        };
+
+        ui.label(RichText::new("Launch options").strong());
+        ui.add(
+            egui::TextEdit::singleline(&mut tab.edit)
+                .hint_text("e.g. WINEDLLOVERRIDES=\"xinput1_4=n,b\" %command% -skipintro")
+                .desired_width(f32::INFINITY),
+        );
+
+        let running = guard::is_running(&tab.task);
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    tab.edit != tab.current && !running,
+                    egui::Button::new("Apply"),
+                )
+                .clicked()
+            {
+                apply(
+                    &app.st.path,
+                    account,
+                    vec![(appid, tab.edit.clone())],
+                    &tab.task,
+                );
+            }
+
+            if ui.button("Apply to multiple games...").clicked() {
+                app.launch_options.options = tab.edit.clone();
+                app.launch_options.selected.insert(appid);
+                app.launch_options.active = true;
+            }
+        });
+
+        if finished(ui, &tab.task) {
+            // Read back what was written
+            tab.appid = None;
+        }
+    }
+}
+
+impl WindowPopup for LaunchOptionsPopup {
+    fn view(app: &mut App, ui: &mut egui::Ui) {
+        let mut active = app.launch_options.active;
+        Window::new("Launch options")
+            .default_size([0.0, 0.0])
+            .open(&mut active)
+            .show(ui, |ui| {
+                let popup = &mut app.launch_options;
+                ui.add(
+                    egui::TextEdit::singleline(&mut popup.options)
+                        .hint_text("Launch options, empty removes them")
+                        .desired_width(f32::INFINITY),
+                );
+                ui.add(egui::TextEdit::singleline(&mut popup.search).hint_text("Search"));
+
+                let mut games: Vec<(u32, String)> = {
+                    app.games
+                        .snapshot()
+                        .games()
+                        .values()
+                        .map(|g| (g.appid, g.details.name.clone()))
+                        .collect()
+                };
+                games.sort_by(|a, b| a.1.cmp(&b.1));
+                let search = popup.search.to_lowercase();
+                games.retain(|(_, name)| name.to_lowercase().contains(&search));
+
+                ui.horizontal(|ui| {
+                    if ui.button("All").clicked() {
+                        popup.selected.extend(games.iter().map(|(id, _)| *id));
+                    }
+                    if ui.button("None").clicked() {
+                        popup.selected.clear();
+                    }
+                    ui.label(format!("{} selected", popup.selected.len()));
+                });
+
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        for (appid, name) in &games {
+                            let mut checked = popup.selected.contains(appid);
+                            if ui.checkbox(&mut checked, name).changed() {
+                                if checked {
+                                    popup.selected.insert(*appid);
+                                } else {
+                                    popup.selected.remove(appid);
+                                }
+                            }
+                        }
+                    });
+
+                if ui
+                    .add_enabled(
+                        !popup.selected.is_empty() && !guard::is_running(&popup.task),
+                        egui::Button::new("Apply to selected"),
+                    )
+                    .clicked()
+                {
+                    let Some(account) = active_user(&app.st.path) else {
+                        rfd::MessageDialog::new()
+                            .set_level(rfd::MessageLevel::Error)
+                            .set_title("Error")
+                            .set_description("No Steam account found in userdata.")
+                            .set_buttons(rfd::MessageButtons::Ok)
+                            .show();
+                        return;
+                    };
+
+                    let options = popup
+                        .selected
+                        .iter()
+                        .map(|appid| (*appid, popup.options.clone()))
+                        .collect();
+                    apply(&app.st.path, account, options, &popup.task);
+                }
+
+                if finished(ui, &popup.task) {
+                    // The tab caches the old value
+                    app.view.launch_options.appid = None;
+                }
+            });
+        app.launch_options.active = active;
+    }
+}
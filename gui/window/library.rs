@@ -0,0 +1,393 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::{App, utils::guard};
+use eframe::egui::{self, RichText};
+use steamtools::{
+    backup::{Backup, create_backup, get_backups, restore_backup},
+    client::{Applied, SteamClient, WriteOp},
+    format_size, format_timestamp,
+    library::{Progress, Stage, library_folders, library_of, move_game},
+    paths,
+};
+
+#[derive(Default)]
+enum TaskState {
+    #[default]
+    Idle,
+    Running(Progress),
+    Done(Result<String, String>),
+}
+
+impl TaskState {
+    fn start() -> Self {
+        TaskState::Running(Progress {
+            stage: Stage::Copying,
+            done: 0,
+            total: 0,
+        })
+    }
+}
+
+/// Adds a failed restart of Steam to the message of a finished task.
+fn with_restart(msg: String, restart: &Option<std::io::Error>) -> String {
+    match restart {
+        Some(e) => format!("{msg}\nSteam could not be started again: {e}"),
+        None => msg,
+    }
+}
+
+/// Shows a running or finished task, returns true while the tab should not
+/// show anything else. `finished` is set once the result was acknowledged.
+fn task_ui(ui: &mut egui::Ui, state: &Mutex<TaskState>, finished: &mut bool) -> bool {
+    let mut state = state.lock().unwrap();
+    match &*state {
+        TaskState::Running(progress) => {
+            let stage = match progress.stage {
+                Stage::Copying => "Copying",
+                Stage::Verifying => "Verifying",
+                Stage::Cleaning => "Removing old files",
+            };
+            ui.label(format!(
+                "{stage}: {} / {}",
+                format_size(progress.done),
+                format_size(progress.total)
+            ));
+            ui.add(egui::ProgressBar::new(
+                progress.done as f32 / progress.total.max(1) as f32,
+            ));
+            ui.request_repaint();
+            true
+        }
+        TaskState::Done(result) => {
+            match result {
+                Ok(msg) => ui.label(msg),
+                Err(e) => ui.colored_label(ui.visuals().error_fg_color, e),
+            };
+            if ui.button("Ok").clicked() {
+                *state = TaskState::Idle;
+                *finished = true;
+            }
+            true
+        }
+        TaskState::Idle => false,
+    }
+}
+
+/// Moves the game shown in the view popup to another library folder.
+#[derive(Default)]
+pub struct MoveTab {
+    appid: Option<u32>,
+    folders: Vec<PathBuf>,
+    current: Option<PathBuf>,
+    target: Option<PathBuf>,
+    state: Arc<Mutex<TaskState>>,
+}
+
+impl MoveTab {
+    fn load(&mut self, steam_path: &str, appid: u32) {
+        self.appid = Some(appid);
+        self.folders = library_folders(steam_path);
+        self.current = library_of(steam_path, appid);
+        self.target = None;
+    }
+
+    pub fn view(app: &mut App, ui: &mut egui::Ui) {
+        let appid = app.view.current_game;
+        let tab = &mut app.view.move_game;
+        if tab.appid != Some(appid) && matches!(*tab.state.lock().unwrap(), TaskState::Idle) {
+            tab.load(&app.st.path, appid);
+        }
+
+        let Some(current) = tab.current.clone() else {
+            ui.label("The game is not installed.");
+            return;
+        };
+
+        ui.label(RichText::new("Library folder").strong());
+        ui.label(current.display().to_string());
+
+        let mut finished = false;
+        if task_ui(ui, &tab.state, &mut finished) {
+            if finished {
+                tab.appid = None;
+            }
+            return;
+        }
+
+        let others: Vec<&PathBuf> = tab.folders.iter().filter(|f| **f != current).collect();
+        if others.is_empty() {
+            ui.label("There are no other library folders.");
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("move_target")
+                .selected_text(
+                    tab.target
+                        .as_ref()
+                        .map_or("Select a library".to_string(), |t| t.display().to_string()),
+                )
+                .show_ui(ui, |ui| {
+                    for folder in &others {
+                        ui.selectable_value(
+                            &mut tab.target,
+                            Some((*folder).clone()),
+                            folder.display().to_string(),
+                        );
+                    }
+                });
+
+            let Some(target) = tab.target.clone() else {
+                return;
+            };
+            if !ui.button("Move").clicked() || !guard::confirm(WriteOp::MoveGame) {
+                return;
+            }
+            let Some(mut game) = app.games.snapshot().get(appid).cloned() else {
+                return;
+            };
+
+            let steam_path = app.st.path.clone();
+            let games = app.games.clone();
+            let state = tab.state.clone();
+            *state.lock().unwrap() = TaskState::start();
+
+            thread::spawn(move || {
+                let client = SteamClient::new(&steam_path);
+                let Applied { result, restart } = client.apply(WriteOp::MoveGame, || {
+                    move_game(&steam_path, &mut game, &target, |progress| {
+                        *state.lock().unwrap() = TaskState::Running(progress);
+                    })
+                });
+
+                let done = match result {
+                    Ok(()) => {
+                        let msg = format!("Moved to {}", game.path);
+                        let saved = games.transaction(|tx| {
+                            tx.insert(game);
+                            Ok(())
+                        });
+                        saved.map(|()| msg).map_err(|e| e.to_string())
+                    }
+                    Err(e) => Err(e.to_string()),
+                };
+                *state.lock().unwrap() = TaskState::Done(
+                    done.map(|msg| with_restart(msg, &restart))
+                        .map_err(|e| with_restart(e, &restart)),
+                );
+            });
+        });
+    }
+}
+
+/// Creates and restores compressed backups of the game shown in the view popup.
+#[derive(Default)]
+pub struct BackupTab {
+    appid: Option<u32>,
+    dir: Option<PathBuf>,
+    installed: bool,
+    folders: Vec<PathBuf>,
+    backups: Vec<Backup>,
+    target: Option<PathBuf>,
+    state: Arc<Mutex<TaskState>>,
+}
+
+impl BackupTab {
+    fn load(&mut self, steam_path: &str, appid: u32) {
+        // Written to paths::backups_dir unless another folder is picked
+        let dir = self.dir.get_or_insert_with(paths::backups_dir);
+        self.appid = Some(appid);
+        self.installed = library_of(steam_path, appid).is_some();
+        self.folders = library_folders(steam_path);
+        self.backups = get_backups(dir)
+            .into_iter()
+            .filter(|b| b.index.appid == appid)
+            .collect();
+        if self.target.is_none() {
+            self.target = self.folders.first().cloned();
+        }
+    }
+
+    pub fn view(app: &mut App, ui: &mut egui::Ui) {
+        let appid = app.view.current_game;
+        let tab = &mut app.view.backup;
+        if tab.appid != Some(appid) && matches!(*tab.state.lock().unwrap(), TaskState::Idle) {
+            tab.load(&app.st.path, appid);
+        }
+
+        let mut finished = false;
+        if task_ui(ui, &tab.state, &mut finished) {
+            if finished {
+                tab.appid = None;
+            }
+            return;
+        }
+
+        let dir = tab.dir.clone().unwrap_or_else(paths::backups_dir);
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Backup folder").strong());
+            ui.label(dir.display().to_string());
+            if ui.button("Change...").clicked()
+                && let Some(picked) = rfd::FileDialog::new()
+                    .set_title("Backup folder")
+                    .pick_folder()
+            {
+                tab.dir = Some(picked);
+                tab.appid = None;
+            }
+        });
+
+        if tab.installed
+            && ui
+                .button("\u{1F4BE} Create backup")
+                .on_hover_text("Archives the game folder and its manifest")
+                .clicked()
+            && let Some(game) = app.games.snapshot().get(appid).cloned()
+        {
+            let steam_path = app.st.path.clone();
+            let state = tab.state.clone();
+            *state.lock().unwrap() = TaskState::start();
+
+            thread::spawn(move || {
+                let result = create_backup(&steam_path, &game, &dir, |progress| {
+                    *state.lock().unwrap() = TaskState::Running(progress);
+                });
+                *state.lock().unwrap() = TaskState::Done(
+                    result
+                        .map(|path| format!("Backup saved to {}", path.display()))
+                        .map_err(|e| e.to_string()),
+                );
+            });
+            return;
+        }
+
+        ui.separator();
+        if tab.backups.is_empty() {
+            ui.label("No backups of this game.");
+            return;
+        }
+
+        if !tab.installed {
+            ui.horizontal(|ui| {
+                ui.label("Restore to");
+                egui::ComboBox::from_id_salt("restore_target")
+                    .selected_text(
+                        tab.target
+                            .as_ref()
+                            .map_or("Select a library".to_string(), |t| t.display().to_string()),
+                    )
+                    .show_ui(ui, |ui| {
+                        for folder in &tab.folders {
+                            ui.selectable_value(
+                                &mut tab.target,
+                                Some(folder.clone()),
+                                folder.display().to_string(),
+                            );
+                        }
+                    });
+            });
+        }
+
+        let mut restore = None;
+        let mut delete = None;
+        egui::Grid::new("backups")
+            .striped(true)
+            .num_columns(4)
+            .show(ui, |ui| {
+                ui.strong("Created");
+                ui.strong("Size");
+                ui.strong("Compressed");
+                ui.end_row();
+
+                for backup in &tab.backups {
+                    ui.label(format_timestamp(backup.index.created));
+                    ui.label(format_size(backup.index.size()));
+                    ui.label(format_size(backup.index.compressed()));
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                !tab.installed && tab.target.is_some(),
+                                egui::Button::new("Restore"),
+                            )
+                            .on_disabled_hover_text("Uninstall the game first")
+                            .clicked()
+                        {
+                            restore = Some(backup.path.clone());
+                        }
+                        if ui.button("\u{1F5D1}").on_hover_text("Delete").clicked() {
+                            delete = Some(backup.path.clone());
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+
+        if let Some(path) = delete
+            && rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Warning)
+                .set_title("Backup")
+                .set_description(format!("Delete {}?", path.display()))
+                .set_buttons(rfd::MessageButtons::YesNo)
+                .show()
+                == rfd::MessageDialogResult::Yes
+        {
+            if let Err(e) = fs::remove_dir_all(&path) {
+                rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Error)
+                    .set_title("Error")
+                    .set_description(e.to_string())
+                    .show();
+            }
+            tab.appid = None;
+        }
+
+        if let Some(path) = restore
+            && let Some(target) = tab.target.clone()
+            && guard::confirm(WriteOp::RestoreBackup)
+        {
+            let steam_path = app.st.path.clone();
+            let games = app.games.clone();
+            let state = tab.state.clone();
+            *state.lock().unwrap() = TaskState::start();
+
+            thread::spawn(move || {
+                let client = SteamClient::new(&steam_path);
+                let Applied { result, restart } = client.apply(WriteOp::RestoreBackup, || {
+                    restore_backup(&steam_path, &path, &target, |progress| {
+                        *state.lock().unwrap() = TaskState::Running(progress);
+                    })
+                });
+
+                let done = match result {
+                    Ok(restored) => {
+                        let msg = format!("Restored to {}", restored.path);
+                        let saved = games.transaction(|tx| {
+                            match tx.get_mut(restored.appid) {
+                                Some(game) => {
+                                    game.installed = true;
+                                    game.path = restored.path;
+                                    game.build_id = restored.build_id;
+                                }
+                                None => {
+                                    tx.insert(restored);
+                                }
+                            }
+                            Ok(())
+                        });
+                        saved.map(|()| msg).map_err(|e| e.to_string())
+                    }
+                    Err(e) => Err(e.to_string()),
+                };
+                *state.lock().unwrap() = TaskState::Done(
+                    done.map(|msg| with_restart(msg, &restart))
+                        .map_err(|e| with_restart(e, &restart)),
+                );
+            });
+        }
+    }
+}
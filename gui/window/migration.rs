@@ -0,0 +1,42 @@
+use crate::{App, window::WindowPopup};
+use eframe::egui::{self, RichText, Window};
+use steamtools::migrate::Report;
+
+/// Shows what happened to the stored data after an update, once.
+#[derive(Default)]
+pub struct MigrationPopup {
+    pub report: Option<Report>,
+}
+
+impl WindowPopup for MigrationPopup {
+    fn view(app: &mut App, ui: &mut egui::Ui) {
+        let Some(report) = &app.migration.report else {
+            return;
+        };
+
+        let mut open = true;
+        let mut close = false;
+        Window::new(format!("Updated to {}", report.to))
+            .default_size([0.0, 0.0])
+            .collapsible(false)
+            .open(&mut open)
+            .show(ui, |ui| {
+                if !report.failed.is_empty() || !report.cleared.is_empty() {
+                    ui.label(
+                        RichText::new("Some data could not be kept, see below.")
+                            .color(ui.visuals().warn_fg_color),
+                    );
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| ui.monospace(report.to_string()));
+                if ui.button("Ok").clicked() {
+                    close = true;
+                }
+            });
+
+        if !open || close {
+            app.migration.report = None;
+        }
+    }
+}
@@ -7,12 +7,31 @@ pub trait WindowPopup {
 mod mods;
 pub use mods::ModsPopup;
 
+mod gallery;
+mod hooks;
+mod library;
+mod proton;
+
+mod changes;
+pub use changes::ChangesPopup;
+
+mod cleanup;
+pub use cleanup::CleanupPopup;
+
+mod migration;
+pub use migration::MigrationPopup;
+
+mod launch_options;
+pub use launch_options::LaunchOptionsPopup;
 mod view;
 pub use view::ViewPopup;
 
 mod settings;
 pub use settings::Settings;
 
+mod transfer;
+pub use transfer::TransferPopup;
+
 mod plugins;
 pub use plugins::Plugins;
 
@@ -1,12 +1,7 @@
-use std::fs::File;
-use std::path::Path;
-
 use eframe::egui;
 use steamtools::install_melonloader;
 
-use crate::STEAM_BINARY_PATH;
 use crate::egui::{TextEdit, Window};
-use crate::utils::bserializer::GameMap;
 use crate::window::WindowPopup;
 
 #[derive(Default)]
@@ -29,19 +24,6 @@ impl WindowPopup for ModsPopup {
                     );
 
                     if ui.button("Get").clicked() {
-                        if app.cached_games.0.is_empty() && !Path::new(STEAM_BINARY_PATH).exists() {
-                            let mut stfile = File::create(STEAM_BINARY_PATH).unwrap();
-                            {
-                                let games_guard = app.games.lock().unwrap();
-                                GameMap::write_to(&mut stfile, &games_guard).unwrap();
-                            }
-                        } else if app.cached_games.0.is_empty()
-                            && Path::new(STEAM_BINARY_PATH).exists()
-                        {
-                            let mut stfile = File::open(STEAM_BINARY_PATH).unwrap();
-                            app.cached_games.0 = GameMap::read_from(&mut stfile).unwrap();
-                        }
-
                         if let Err(_) = app.st.mod_id.parse::<u32>() {
                             rfd::MessageDialog::new()
                                 .set_level(rfd::MessageLevel::Warning)
@@ -50,12 +32,20 @@ impl WindowPopup for ModsPopup {
                                 .set_description("APPID should be numeric")
                                 .show();
                         } else {
-                            if let Some(s) = app
-                                .cached_games
-                                .0
-                                .get(&app.st.mod_id.parse::<u32>().unwrap())
-                            {
-                                install_melonloader(&s.path, app.st.melon_loader);
+                            let path = app
+                                .games
+                                .snapshot()
+                                .get(app.st.mod_id.parse::<u32>().unwrap())
+                                .map(|g| g.path.clone());
+                            if let Some(path) = path {
+                                if let Err(e) = install_melonloader(&path, app.st.melon_loader) {
+                                    rfd::MessageDialog::new()
+                                        .set_level(rfd::MessageLevel::Error)
+                                        .set_buttons(rfd::MessageButtons::Ok)
+                                        .set_title("Error")
+                                        .set_description(e.to_string())
+                                        .show();
+                                }
                             } else {
                                 rfd::MessageDialog::new()
                                     .set_title("Info")
@@ -65,9 +55,6 @@ impl WindowPopup for ModsPopup {
                                     .show();
                             }
                         }
-
-                        // TODO: Add Game serialization to binary?
-                        // todo!("Cant get path without saving Game struct");
                     };
                 });
             });
@@ -1,7 +1,7 @@
 use std::{
     fs,
     ops::{Index, IndexMut},
-    path::Path,
+    path::PathBuf,
     sync::{Arc, Mutex},
     thread,
 };
@@ -9,7 +9,14 @@ use std::{
 use crate::{App, window::WindowPopup};
 use eframe::egui::{self, FontId, Label, RichText};
 use egui_code_editor::{CodeEditor, ColorTheme, Syntax};
-use steamtools::st::{run_lua_file, start_file, stop_file};
+use steamtools::{
+    atomic, paths,
+    st::{run_lua_file, start_file, stop_file},
+};
+
+fn plugin_path(name: &str) -> PathBuf {
+    paths::plugins_dir().join(format!("{name}.lua"))
+}
 
 #[derive(Default)]
 pub struct Plugin {
@@ -34,10 +41,11 @@ impl WindowPopup for Plugins {
             .open(&mut app.plugins.active)
             .show(ui, |ui| {
                 if !app.plugins.fetched {
-                    if !Path::new("./plugins").exists() {
-                        fs::create_dir("./plugins").unwrap()
+                    let plugins_dir = paths::plugins_dir();
+                    if !plugins_dir.exists() {
+                        fs::create_dir_all(&plugins_dir).unwrap()
                     }
-                    let dirs = fs::read_dir("./plugins").unwrap();
+                    let dirs = fs::read_dir(&plugins_dir).unwrap();
                     for dir in dirs {
                         match dir {
                             Ok(d) => {
@@ -64,16 +72,14 @@ impl WindowPopup for Plugins {
                 if app.plugins.list.is_empty() {
                     ui.centered_and_justified(|ui| {
                         if ui.button("Add new plugin").clicked() {
-                            let mut dir = std::env::current_dir().unwrap();
-                            dir.push("plugins");
                             let plugin = rfd::FileDialog::default()
-                                .set_directory(dir)
+                                .set_directory(paths::plugins_dir())
                                 .set_file_name("plugin.lua")
                                 .add_filter("Lua File", &[".lua"])
                                 .save_file();
 
                             if let Some(f) = plugin {
-                                fs::File::create(f).unwrap();
+                                atomic::write(f, "").unwrap();
                             }
 
                             app.plugins.fetched = false;
@@ -110,7 +116,11 @@ impl WindowPopup for Plugins {
                                         let pl = plugin.name.clone();
                                         thread::spawn(move || {
                                             let pl_guard = pl.lock().unwrap();
-                                            run_lua_file(format!("./plugins/{}.lua", *pl_guard));
+                                            run_lua_file(
+                                                plugin_path(&pl_guard)
+                                                    .to_string_lossy()
+                                                    .into_owned(),
+                                            );
                                         });
                                     }
 
@@ -151,14 +161,13 @@ impl Plugins {
                         ui.horizontal(|ui| {
                             let len = app.plugins.list.len();
                             if ui.button("\u{1F5D1}").clicked() {
-                                if let Err(e) = fs::remove_file(format!(
-                                    "./plugins/{}.lua",
-                                    app.plugins
+                                if let Err(e) = fs::remove_file(plugin_path(
+                                    &app.plugins
                                         .list
                                         .remove(app.plugins.get().unwrap())
                                         .name
                                         .lock()
-                                        .unwrap()
+                                        .unwrap(),
                                 )) {
                                     eprintln!("{}", e);
                                 }
@@ -172,21 +181,14 @@ impl Plugins {
                                 || ui.input(|i| i.modifiers.ctrl && i.key_pressed(egui::Key::S))
                             {
                                 fs::rename(
-                                    format!(
-                                        "./plugins/{}.lua",
-                                        &app.plugins.get_selected().unwrap().name.lock().unwrap()
-                                    ),
-                                    format!(
-                                        "./plugins/{}.lua",
-                                        &app.plugins.get_selected().unwrap().name_buffer
+                                    plugin_path(
+                                        &app.plugins.get_selected().unwrap().name.lock().unwrap(),
                                     ),
+                                    plugin_path(&app.plugins.get_selected().unwrap().name_buffer),
                                 )
                                 .ok();
-                                fs::write(
-                                    format!(
-                                        "./plugins/{}.lua",
-                                        &app.plugins.get_selected().unwrap().name_buffer
-                                    ),
+                                atomic::write(
+                                    plugin_path(&app.plugins.get_selected().unwrap().name_buffer),
                                     app.plugins.get_selected().unwrap().code.as_bytes(),
                                 )
                                 .unwrap();
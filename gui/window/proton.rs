@@ -0,0 +1,248 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    App,
+    utils::guard::{self, SharedTask},
+};
+use eframe::egui::{self, RichText};
+use log::error;
+use steamtools::{
+    client::WriteOp,
+    format_size,
+    proton::{
+        CompatTool, Prefix, delete_prefix, get_compat_tool, get_compat_tools, get_prefixes,
+        reset_prefix, set_compat_tool,
+    },
+};
+
+#[derive(Default)]
+pub struct CompatTools {
+    appid: Option<u32>,
+    tools: Vec<CompatTool>,
+    current: Option<String>,
+    selected: Option<String>,
+    /// Returns the tool that was set.
+    task: SharedTask<Option<String>>,
+}
+
+#[derive(Default)]
+pub struct Prefixes {
+    loaded: bool,
+    list: Arc<Mutex<Option<Vec<Prefix>>>>,
+    /// A reset or delete, removing a large prefix takes a while.
+    task: SharedTask<()>,
+}
+
+/// Asks before running a destructive action on a prefix.
+fn confirm(description: String) -> bool {
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Proton prefix")
+        .set_description(description)
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+        == rfd::MessageDialogResult::Yes
+}
+
+/// Open, reset and delete buttons. Reset and delete run on a thread, see
+/// [`guard::apply`].
+fn actions(ui: &mut egui::Ui, prefix: &Prefix, steam_path: &str, task: &SharedTask<()>) {
+    if ui.button("\u{1F4C2} Open").clicked()
+        && let Err(e) = steamtools::open(&prefix.path)
+    {
+        error!("Open prefix: {e}");
+    }
+
+    let idle = !guard::is_running(task);
+    let reset = ui
+        .add_enabled(idle, egui::Button::new("Reset"))
+        .on_hover_text("Empties the prefix, Proton creates a new one on the next launch")
+        .clicked();
+    let delete = ui
+        .add_enabled(idle, egui::Button::new("\u{1F5D1} Delete"))
+        .clicked();
+
+    let prefix = prefix.clone();
+    if reset
+        && confirm(format!(
+            "Reset the prefix of {}? Settings and saves stored inside it are lost.",
+            prefix.appid
+        ))
+    {
+        guard::apply(steam_path, WriteOp::Prefix, task, move || {
+            reset_prefix(&prefix)
+        });
+    } else if delete
+        && confirm(format!(
+            "Delete the prefix of {} ({})?",
+            prefix.appid,
+            format_size(prefix.size)
+        ))
+    {
+        guard::apply(steam_path, WriteOp::Prefix, task, move || {
+            delete_prefix(&prefix)
+        });
+    }
+}
+
+impl Prefixes {
+    fn load(&mut self, steam_path: String) {
+        self.loaded = true;
+        let list = Arc::new(Mutex::new(None));
+        self.list = list.clone();
+
+        // Measuring every prefix can take a while
+        thread::spawn(move || {
+            *list.lock().unwrap() = Some(get_prefixes(&steam_path));
+        });
+    }
+
+    pub fn view(app: &mut App, ui: &mut egui::Ui) {
+        if !app.view.prefixes.loaded {
+            app.view.prefixes.load(app.st.path.clone());
+        }
+
+        let Some(prefixes) = app.view.prefixes.list.lock().unwrap().clone() else {
+            ui.spinner();
+            ui.request_repaint();
+            return;
+        };
+
+        let appid = app.view.current_game;
+        let (steam_path, task) = (&app.st.path, &app.view.prefixes.task);
+
+        ui.label(RichText::new("Prefix").strong());
+        match prefixes.iter().find(|p| p.appid == appid) {
+            Some(prefix) => {
+                ui.label(prefix.path.display().to_string());
+                ui.label(format!("Size: {}", format_size(prefix.size)));
+                if prefix.orphaned {
+                    ui.label("The game is not installed anymore, the prefix is orphaned.");
+                }
+                ui.horizontal(|ui| actions(ui, prefix, steam_path, task));
+            }
+            None => {
+                ui.label("This game has no Proton prefix.");
+            }
+        }
+
+        let orphaned: Vec<&Prefix> = prefixes
+            .iter()
+            .filter(|p| p.orphaned && p.appid != appid)
+            .collect();
+        if !orphaned.is_empty() {
+            ui.separator();
+            ui.collapsing(
+                format!(
+                    "Orphaned prefixes ({}, {})",
+                    orphaned.len(),
+                    format_size(orphaned.iter().map(|p| p.size).sum())
+                ),
+                |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(200.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("orphaned_prefixes")
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for prefix in orphaned {
+                                        ui.label(prefix.appid.to_string());
+                                        ui.label(format_size(prefix.size));
+                                        ui.horizontal(|ui| actions(ui, prefix, steam_path, task));
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                },
+            );
+        }
+
+        let changed = match guard::poll(ui, task) {
+            Some(Err(e)) => {
+                rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Error)
+                    .set_title("Error")
+                    .set_description(e.to_string())
+                    .set_buttons(rfd::MessageButtons::Ok)
+                    .show();
+                true
+            }
+            Some(Ok(())) => true,
+            None => false,
+        };
+        if ui.button("\u{1F502} Reload").clicked() || changed {
+            app.view.prefixes.loaded = false;
+        }
+    }
+}
+
+impl CompatTools {
+    fn load(&mut self, steam_path: &str, appid: u32) {
+        self.appid = Some(appid);
+        self.tools = get_compat_tools(steam_path);
+        self.current = get_compat_tool(steam_path, appid);
+        self.selected = self.current.clone();
+    }
+
+    pub fn view(app: &mut App, ui: &mut egui::Ui) {
+        let appid = app.view.current_game;
+        let compat = &mut app.view.compat;
+        if compat.appid != Some(appid) && !guard::is_running(&compat.task) {
+            compat.load(&app.st.path, appid);
+        }
+
+        ui.label(RichText::new("Compatibility tool").strong());
+        let selected_text = match &compat.selected {
+            Some(name) => compat
+                .tools
+                .iter()
+                .find(|t| &t.name == name)
+                .map_or_else(|| name.clone(), |t| t.display_name.clone()),
+            None => "Default".to_string(),
+        };
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("compat_tool")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut compat.selected, None, "Default");
+                    for tool in &compat.tools {
+                        ui.selectable_value(
+                            &mut compat.selected,
+                            Some(tool.name.clone()),
+                            &tool.display_name,
+                        );
+                    }
+                });
+
+            if ui
+                .add_enabled(
+                    compat.selected != compat.current && !guard::is_running(&compat.task),
+                    egui::Button::new("Apply"),
+                )
+                .clicked()
+            {
+                let (steam_path, selected) = (app.st.path.clone(), compat.selected.clone());
+                guard::apply(&app.st.path, WriteOp::CompatTool, &compat.task, move || {
+                    set_compat_tool(&steam_path, appid, selected.as_deref()).map(|()| selected)
+                });
+            }
+        });
+
+        match guard::poll(ui, &compat.task) {
+            Some(Ok(tool)) => compat.current = tool,
+            Some(Err(e)) => {
+                rfd::MessageDialog::new()
+                    .set_level(rfd::MessageLevel::Error)
+                    .set_title("Error")
+                    .set_description(e.to_string())
+                    .set_buttons(rfd::MessageButtons::Ok)
+                    .show();
+            }
+            None => (),
+        }
+    }
+}
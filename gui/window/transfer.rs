@@ -0,0 +1,156 @@
+use crate::{App, window::WindowPopup};
+use eframe::egui::{self, RichText, Window};
+use steamtools::{
+    Steam,
+    migrate::State,
+    transfer::{self, Action, Archive, Mode, Planned},
+};
+
+/// Export and import of the settings, see [`steamtools::transfer`].
+#[derive(Default)]
+pub struct TransferPopup {
+    /// Also exports the artwork cache.
+    pub artwork: bool,
+    archive: Option<Archive>,
+    mode: Option<Mode>,
+    planned: Vec<Planned>,
+}
+
+fn show_error(e: impl ToString) {
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title("Error")
+        .set_description(e.to_string())
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
+}
+
+/// The values that make sense on another machine, the Steam path and config
+/// folder stay behind.
+fn portable_state(app: &App) -> State {
+    let steam = Steam {
+        mod_id: app.st.mod_id.clone(),
+        melon_loader: app.st.melon_loader,
+        ..Default::default()
+    };
+    State::from([
+        (
+            "settings".to_string(),
+            serde_json::to_string(&app.settings).unwrap(),
+        ),
+        (
+            "unlock".to_string(),
+            serde_json::to_string(&app.unlock).unwrap(),
+        ),
+        ("steam".to_string(), serde_json::to_string(&steam).unwrap()),
+    ])
+}
+
+/// Loads an imported state, missing values go back to their defaults.
+fn load_state(app: &mut App, state: &State) {
+    let get = |key: &str| state.get(key).map(String::as_str).unwrap_or("null");
+    app.settings = serde_json::from_str(get("settings")).unwrap_or_default();
+    app.unlock = serde_json::from_str(get("unlock")).unwrap_or_default();
+    let steam: Steam = serde_json::from_str(get("steam")).unwrap_or_default();
+    app.st.mod_id = steam.mod_id;
+    app.st.melon_loader = steam.melon_loader;
+    // New plugin files
+    app.plugins.fetched = false;
+}
+
+impl TransferPopup {
+    pub fn export(app: &App) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name("steamtools.tar.gz")
+            .add_filter("Archive", &["gz"])
+            .save_file()
+        else {
+            return;
+        };
+        if let Err(e) = transfer::export(&path, &portable_state(app), app.transfer.artwork) {
+            show_error(format!("Exporting to {}: {e}", path.display()));
+        }
+    }
+
+    pub fn import(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Archive", &["gz"])
+            .pick_file()
+        else {
+            return;
+        };
+        match Archive::read(&path) {
+            Ok(archive) => {
+                self.archive = Some(archive);
+                self.mode = None;
+            }
+            Err(e) => show_error(format!("Reading {}: {e}", path.display())),
+        }
+    }
+}
+
+impl WindowPopup for TransferPopup {
+    fn view(app: &mut App, ui: &mut egui::Ui) {
+        let Some(archive) = app.transfer.archive.take() else {
+            return;
+        };
+
+        let current = portable_state(app);
+        let mut mode = app.transfer.mode.unwrap_or(Mode::Merge);
+        let mut open = true;
+        let mut apply = false;
+        let mut cancel = false;
+
+        Window::new("Import settings")
+            .default_size([0.0, 0.0])
+            .collapsible(false)
+            .open(&mut open)
+            .show(ui, |ui| {
+                ui.label(format!("Exported by Steamtools {}", archive.version));
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut mode, Mode::Merge, "Merge")
+                        .on_hover_text("Adds and updates, keeps everything else");
+                    ui.radio_value(&mut mode, Mode::Replace, "Replace")
+                        .on_hover_text("Also removes what the archive doesn't have");
+                });
+
+                // Only reads the folders again when the mode changes
+                if app.transfer.mode != Some(mode) {
+                    app.transfer.planned = archive.preview(&current, mode);
+                    app.transfer.mode = Some(mode);
+                }
+
+                ui.separator();
+                if app.transfer.planned.is_empty() {
+                    ui.label("Nothing to change");
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for planned in &app.transfer.planned {
+                            let text = RichText::new(planned.to_string());
+                            ui.label(match planned.action {
+                                Action::Remove => text.color(ui.visuals().warn_fg_color),
+                                _ => text,
+                            });
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    apply = ui
+                        .add_enabled(!app.transfer.planned.is_empty(), egui::Button::new("Apply"))
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+
+        if apply {
+            match archive.apply(&current, mode) {
+                Ok(state) => load_state(app, &state),
+                Err(e) => show_error(format!("Importing: {e}")),
+            }
+        } else if open && !cancel {
+            app.transfer.archive = Some(archive);
+        }
+    }
+}
@@ -1,12 +1,37 @@
-use std::fmt::Write;
+use std::{
+    fmt::Write,
+    sync::{Arc, Mutex},
+    thread,
+};
 
 use crate::{App, window::WindowPopup};
 use eframe::egui;
+use steamtools::{
+    format_size, format_timestamp,
+    sessions::format_duration,
+    workshop::{WorkshopItem, get_workshop_items},
+};
+
+use super::{
+    gallery::Gallery,
+    hooks::HooksTab,
+    launch_options::LaunchOptionsTab,
+    library::{BackupTab, MoveTab},
+    proton::{CompatTools, Prefixes},
+};
 
 #[derive(Debug, Default)]
 pub enum ViewState {
     #[default]
     Main,
+    Screenshots,
+    Workshop,
+    Proton,
+    LaunchOptions,
+    Move,
+    Backup,
+    Sessions,
+    Hooks,
 }
 
 #[derive(Default)]
@@ -14,19 +39,186 @@ pub struct ViewPopup {
     pub active: bool,
     pub current_game: u32,
     pub state: ViewState,
+    pub gallery: Gallery,
+    /// Filled by a thread, measuring the folders takes a while.
+    pub workshop: Option<(u32, Arc<Mutex<Option<Vec<WorkshopItem>>>>)>,
+    pub prefixes: Prefixes,
+    pub compat: CompatTools,
+    pub launch_options: LaunchOptionsTab,
+    pub move_game: MoveTab,
+    pub backup: BackupTab,
+    pub hooks: HooksTab,
+}
+
+fn workshop(app: &mut App, ui: &mut egui::Ui) {
+    let appid = app.view.current_game;
+    if app
+        .view
+        .workshop
+        .as_ref()
+        .is_none_or(|(id, _)| *id != appid)
+    {
+        let items = Arc::new(Mutex::new(None));
+        let (loaded, path) = (items.clone(), app.st.path.clone());
+        thread::spawn(move || {
+            *loaded.lock().unwrap() = Some(get_workshop_items(path, appid));
+        });
+        app.view.workshop = Some((appid, items));
+    }
+    let Some(items) = app
+        .view
+        .workshop
+        .as_ref()
+        .and_then(|(_, items)| items.lock().unwrap().clone())
+    else {
+        ui.spinner();
+        ui.request_repaint();
+        return;
+    };
+
+    let mut reload = false;
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "{} items, {}",
+            items.len(),
+            format_size(items.iter().map(|i| i.size).sum())
+        ));
+        reload = ui.button("\u{1F502} Reload").clicked();
+    });
+    if reload {
+        app.view.workshop = None;
+        return;
+    }
+
+    if items.is_empty() {
+        ui.label("No workshop items found.");
+        return;
+    }
+
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .show(ui, |ui| {
+            egui::Grid::new("workshop_items")
+                .striped(true)
+                .num_columns(5)
+                .show(ui, |ui| {
+                    ui.strong("ID");
+                    ui.strong("Size");
+                    ui.strong("Updated");
+                    ui.strong("Status");
+                    // Open folder
+                    ui.label("");
+                    ui.end_row();
+
+                    for item in &items {
+                        ui.label(item.id.to_string());
+                        ui.label(format_size(item.size));
+                        ui.label(format_timestamp(item.updated));
+                        ui.label(match (item.subscribed, item.installed) {
+                            (true, true) => "Subscribed",
+                            (true, false) => "Not downloaded",
+                            (false, true) => "Unsubscribed",
+                            (false, false) => "-",
+                        });
+                        if let Some(path) = &item.path
+                            && ui
+                                .button("\u{1F4C2}")
+                                .on_hover_text(path.display().to_string())
+                                .clicked()
+                        {
+                            steamtools::open(path).ok();
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+fn sessions(app: &mut App, ui: &mut egui::Ui) {
+    let appid = app.view.current_game;
+    let (sessions, playtime, running) = {
+        let tracker = app.sessions.lock().unwrap();
+        (
+            tracker.sessions(appid),
+            tracker.playtime(appid),
+            tracker.is_running(appid),
+        )
+    };
+
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "{} sessions, {} played",
+            sessions.len(),
+            format_duration(playtime)
+        ));
+        if running {
+            ui.colored_label(egui::Color32::LIGHT_GREEN, "\u{25B6} Running");
+        }
+    });
+
+    if sessions.is_empty() {
+        ui.label("No sessions recorded yet.");
+        return;
+    }
+
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .show(ui, |ui| {
+            egui::Grid::new("sessions")
+                .striped(true)
+                .num_columns(3)
+                .show(ui, |ui| {
+                    ui.strong("Started");
+                    ui.strong("Ended");
+                    ui.strong("Duration");
+                    ui.end_row();
+
+                    for session in &sessions {
+                        ui.label(format_timestamp(session.start));
+                        ui.label(session.end.map_or("-".to_string(), format_timestamp));
+                        ui.label(format_duration(session.duration()));
+                        ui.end_row();
+                    }
+                });
+        });
 }
 
 impl WindowPopup for ViewPopup {
     fn view(app: &mut App, ui: &mut egui::Ui) {
+        let mut active = app.view.active;
         egui::Window::new("View")
             .default_size([0.0, 0.0])
-            .open(&mut app.view.active)
+            .open(&mut active)
             .show(ui, |ui| {
                 ui.vertical_centered(|ui| {
                     ui.horizontal(|ui| {
                         if ui.button("\u{1F3E0} Home").clicked() {
                             app.view.state = ViewState::Main
                         };
+                        if ui.button("\u{1F4F7} Screenshots").clicked() {
+                            app.view.state = ViewState::Screenshots
+                        };
+                        if ui.button("\u{1F527} Workshop").clicked() {
+                            app.view.state = ViewState::Workshop
+                        };
+                        if cfg!(target_os = "linux") && ui.button("\u{1F377} Proton").clicked() {
+                            app.view.state = ViewState::Proton
+                        };
+                        if ui.button("\u{1F680} Launch options").clicked() {
+                            app.view.state = ViewState::LaunchOptions
+                        };
+                        if ui.button("\u{1F4E6} Move").clicked() {
+                            app.view.state = ViewState::Move
+                        };
+                        if ui.button("\u{1F4BE} Backup").clicked() {
+                            app.view.state = ViewState::Backup
+                        };
+                        if ui.button("\u{23F1} Sessions").clicked() {
+                            app.view.state = ViewState::Sessions
+                        };
+                        if ui.button("\u{1F4DC} Hooks").clicked() {
+                            app.view.state = ViewState::Hooks
+                        };
                     });
                 });
                 match app.view.state {
@@ -38,7 +230,20 @@ impl WindowPopup for ViewPopup {
                             app.buffer.clear();
                         });
                     }
+                    ViewState::Screenshots => Gallery::view(app, ui),
+                    ViewState::Workshop => workshop(app, ui),
+                    ViewState::Proton => {
+                        CompatTools::view(app, ui);
+                        ui.separator();
+                        Prefixes::view(app, ui);
+                    }
+                    ViewState::LaunchOptions => LaunchOptionsTab::view(app, ui),
+                    ViewState::Move => MoveTab::view(app, ui),
+                    ViewState::Backup => BackupTab::view(app, ui),
+                    ViewState::Sessions => sessions(app, ui),
+                    ViewState::Hooks => HooksTab::view(app, ui),
                 }
             });
+        app.view.active = active;
     }
 }
@@ -0,0 +1,192 @@
+//! # atomic
+//!
+//! Crash safe file writes. The data goes to a temporary file next to the
+//! target, is synced to disk and then renamed over the target, so a crash or
+//! kill leaves either the old or the new file but never half of one.
+//!
+//! ```no_run
+//! use steamtools::atomic;
+//!
+//! atomic::write("hooks.json", "{}")?;
+//! // Keeps the previous file as `library.db.bak`
+//! atomic::Writer::new("library.db").backup(true).write(b"...")?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use log::warn;
+
+/// Where [`Writer::backup`] keeps the previous file, `<name>.bak`.
+pub fn backup_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_os_string();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// A temporary file of its own for every write, threads writing the same
+/// file at once must not share one.
+fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
+
+/// Writes a file in one step, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Writer {
+    path: PathBuf,
+    backup: bool,
+}
+
+impl Writer {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            backup: false,
+        }
+    }
+
+    /// Keeps the file being replaced at [`backup_path`].
+    pub fn backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    pub fn write(&self, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        self.write_with(|out| out.write_all(contents.as_ref()))
+    }
+
+    /// Lets `f` stream the contents. Nothing is replaced if it fails.
+    pub fn write_with<T>(
+        &self,
+        f: impl FnOnce(&mut BufWriter<File>) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let tmp = temp_path(&self.path);
+        let result = (|| {
+            let mut out = BufWriter::new(File::create(&tmp)?);
+            let result = f(&mut out)?;
+            out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+
+            // Through a temporary file as well, a half copied backup would
+            // be trusted by `read_with_backup`
+            if self.backup && self.path.exists() {
+                copy(&self.path, backup_path(&self.path))?;
+            }
+            fs::rename(&tmp, &self.path)?;
+            Ok(result)
+        })();
+
+        if result.is_err() {
+            fs::remove_file(&tmp).ok();
+        } else {
+            sync_dir(&self.path);
+        }
+        result
+    }
+}
+
+/// Makes the rename itself durable, only needed (and possible) on unix.
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        if let Ok(dir) = File::open(parent) {
+            dir.sync_all().ok();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Like [`fs::write`], but see the [module docs](self).
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    Writer::new(path).write(contents)
+}
+
+/// Like [`fs::copy`], but `to` only appears once it is complete.
+pub fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<u64> {
+    let mut src = File::open(from)?;
+    Writer::new(to).write_with(|out| io::copy(&mut src, out))
+}
+
+/// Reads a file, falling back to its backup if it is damaged, that is `parse`
+/// fails with [`io::ErrorKind::InvalidData`] or the file ends early.
+pub fn read_with_backup<T>(
+    path: impl AsRef<Path>,
+    parse: impl Fn(&[u8]) -> io::Result<T>,
+) -> io::Result<T> {
+    let read = |path: &Path| -> io::Result<T> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        parse(&data)
+    };
+    let path = path.as_ref();
+    match read(path) {
+        Ok(value) => Ok(value),
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+            ) =>
+        {
+            warn!("Reading {}: {e}, trying the backup", path.display());
+            read(&backup_path(path)).map_err(|_| e)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn replaces_with_backup() {
+        let dir = TempDir::new("atomic");
+        let path = dir.join("file.txt");
+        write(&path, "first").unwrap();
+        Writer::new(&path).backup(true).write("second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "first");
+
+        // A failing writer leaves the file alone
+        let failed = Writer::new(&path).write_with(|out| {
+            out.write_all(b"half")?;
+            Err::<(), _>(io::Error::other("killed"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        let prefix = format!(".{}.", path.file_name().unwrap().to_string_lossy());
+        let parent = path.parent().unwrap();
+        assert!(!fs::read_dir(parent).unwrap().any(|e| {
+            e.unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&prefix)
+        }));
+
+        // Damaged files fall back to the backup
+        fs::write(&path, "garbage").unwrap();
+        let read = read_with_backup(&path, |data| match data {
+            b"garbage" => Err(io::Error::new(io::ErrorKind::InvalidData, "damaged")),
+            data => Ok(String::from_utf8_lossy(data).to_string()),
+        });
+        assert_eq!(read.unwrap(), "first");
+    }
+}
@@ -0,0 +1,479 @@
+//! # backup
+//!
+//! Archives of installed games. A backup is a folder with an `index.json`
+//! describing every file and a number of gzip compressed chunks holding the
+//! file contents back to back, so large games don't end up in one huge file.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::atomic;
+use crate::client::{WriteOp, guard};
+use crate::library::{
+    Progress, Stage, install_dir, library_of, manifest_path, update_libraryfolders,
+};
+use crate::vdf;
+use crate::{AppData, Game, dir_size};
+
+pub const INDEX_FILE: &str = "index.json";
+const INDEX_VERSION: u32 = 1;
+/// Uncompressed bytes stored per chunk.
+const CHUNK_SIZE: u64 = 1 << 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Relative to the install folder, always separated by `/`.
+    pub path: String,
+    pub size: u64,
+    pub crc: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub file: String,
+    pub size: u64,
+    pub compressed: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Index {
+    pub version: u32,
+    pub appid: u32,
+    pub name: String,
+    pub installdir: String,
+    pub created: u64,
+    /// Contents of `appmanifest_<appid>.acf`.
+    pub manifest: String,
+    /// Every folder, including empty ones, parents come first.
+    pub dirs: Vec<String>,
+    pub files: Vec<Entry>,
+    pub chunks: Vec<Chunk>,
+}
+
+impl Index {
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+
+    pub fn compressed(&self) -> u64 {
+        self.chunks.iter().map(|c| c.compressed).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub index: Index,
+}
+
+/// Splits everything written to it into compressed chunks of `chunk_size`
+/// bytes, [`CHUNK_SIZE`] outside of tests.
+struct ChunkWriter {
+    dir: PathBuf,
+    chunk_size: u64,
+    current: Option<GzEncoder<BufWriter<File>>>,
+    written: u64,
+    chunks: Vec<Chunk>,
+}
+
+impl ChunkWriter {
+    fn new(dir: &Path, chunk_size: u64) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            chunk_size,
+            current: None,
+            written: 0,
+            chunks: Vec::new(),
+        }
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        let Some(encoder) = self.current.take() else {
+            return Ok(());
+        };
+        let file = encoder.finish()?.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        if let Some(chunk) = self.chunks.last_mut() {
+            chunk.size = self.written;
+            chunk.compressed = file.metadata()?.len();
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<Vec<Chunk>> {
+        self.close()?;
+        Ok(self.chunks)
+    }
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.current.is_some() && self.written >= self.chunk_size {
+            self.close()?;
+        }
+        if self.current.is_none() {
+            // Not atomic, a backup only counts once its index is written
+            let file = format!("{:04}.gz", self.chunks.len());
+            let out = BufWriter::new(File::create(self.dir.join(&file))?);
+            self.current = Some(GzEncoder::new(out, Compression::default()));
+            self.written = 0;
+            self.chunks.push(Chunk {
+                file,
+                size: 0,
+                compressed: 0,
+            });
+        }
+
+        let n = buf.len().min((self.chunk_size - self.written) as usize);
+        let n = self.current.as_mut().unwrap().write(&buf[..n])?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.current.as_mut().map_or(Ok(()), |c| c.flush())
+    }
+}
+
+/// Reads the chunks of a backup as one continuous stream.
+struct ChunkReader<'a> {
+    dir: &'a Path,
+    chunks: std::slice::Iter<'a, Chunk>,
+    current: Option<GzDecoder<BufReader<File>>>,
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.current.is_none() {
+                let Some(chunk) = self.chunks.next() else {
+                    return Ok(0);
+                };
+                let file = File::open(self.dir.join(&chunk.file))?;
+                self.current = Some(GzDecoder::new(BufReader::new(file)));
+            }
+
+            let n = self.current.as_mut().unwrap().read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            self.current = None;
+        }
+    }
+}
+
+/// Lists the folders and files below `root` in a stable order.
+fn walk(root: &Path, rel: &str, dirs: &mut Vec<String>, files: &mut Vec<String>) -> io::Result<()> {
+    let mut entries = fs::read_dir(root.join(rel))?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = if rel.is_empty() {
+            name
+        } else {
+            format!("{rel}/{name}")
+        };
+        if entry.file_type()?.is_dir() {
+            dirs.push(path.clone());
+            walk(root, &path, dirs, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Copies `size` bytes from `from` to `to` and returns their CRC32.
+fn copy_exact(
+    from: &mut impl Read,
+    to: &mut impl Write,
+    size: u64,
+    on_copy: &mut impl FnMut(u64),
+) -> io::Result<u32> {
+    let mut buf = vec![0u8; 1 << 20];
+    let mut hasher = crc32fast::Hasher::new();
+    let mut left = size;
+    while left > 0 {
+        let want = buf.len().min(left as usize);
+        let n = from.read(&mut buf[..want])?;
+        if n == 0 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "File ended early"));
+        }
+        hasher.update(&buf[..n]);
+        to.write_all(&buf[..n])?;
+        left -= n as u64;
+        on_copy(n as u64);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Archives an installed game into a new folder inside `dir` and returns it.
+pub fn create_backup(
+    path: impl AsRef<Path>,
+    game: &Game,
+    dir: impl AsRef<Path>,
+    mut progress: impl FnMut(Progress),
+) -> io::Result<PathBuf> {
+    guard(WriteOp::CreateBackup)?;
+    let library = library_of(&path, game.appid)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Game is not installed"))?;
+    let installdir = install_dir(&library, game.appid)?;
+    let source = library.join("steamapps").join("common").join(&installdir);
+    let manifest = fs::read_to_string(manifest_path(&library, game.appid))?;
+
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let target = dir.as_ref().join(format!("{}_{created}", game.appid));
+    fs::create_dir_all(&target)?;
+    info!("Backing up {} to {}", source.display(), target.display());
+
+    let result = (|| {
+        let mut dirs = Vec::new();
+        let mut names = Vec::new();
+        walk(&source, "", &mut dirs, &mut names)?;
+
+        let total = dir_size(&source);
+        let mut done = 0;
+        let mut on_copy = |n| {
+            done += n;
+            progress(Progress {
+                stage: Stage::Copying,
+                done,
+                total,
+            });
+        };
+
+        let mut out = ChunkWriter::new(&target, CHUNK_SIZE);
+        let mut files = Vec::with_capacity(names.len());
+        for name in names {
+            let mut file = File::open(source.join(&name))?;
+            let size = file.metadata()?.len();
+            let crc = copy_exact(&mut file, &mut out, size, &mut on_copy)?;
+            files.push(Entry {
+                path: name,
+                size,
+                crc,
+            });
+        }
+
+        let index = Index {
+            version: INDEX_VERSION,
+            appid: game.appid,
+            name: game.details.name.clone(),
+            installdir,
+            created,
+            manifest,
+            dirs,
+            files,
+            chunks: out.finish()?,
+        };
+        let json = serde_json::to_string_pretty(&index)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        atomic::write(target.join(INDEX_FILE), json)
+    })();
+
+    if let Err(e) = result {
+        fs::remove_dir_all(&target).ok();
+        return Err(e);
+    }
+    Ok(target)
+}
+
+/// Checks that a path from an index stays inside the folder it is joined to,
+/// only plain names are allowed. `single` also rejects nested paths.
+fn check_path(path: &str, single: bool) -> io::Result<()> {
+    let components: Vec<Component> = Path::new(path).components().collect();
+    if components.is_empty()
+        || (single && components.len() > 1)
+        || !components.iter().all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid path {path:?} in the backup"),
+        ));
+    }
+    Ok(())
+}
+
+/// Reads and validates the index, a backup never writes outside of its
+/// install folder.
+pub fn read_index(backup: impl AsRef<Path>) -> io::Result<Index> {
+    let text = fs::read_to_string(backup.as_ref().join(INDEX_FILE))?;
+    let index: Index =
+        serde_json::from_str(&text).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    if index.version > INDEX_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Backup version {} is not supported", index.version),
+        ));
+    }
+
+    check_path(&index.installdir, true)?;
+    for chunk in &index.chunks {
+        check_path(&chunk.file, true)?;
+    }
+    for path in index.dirs.iter().chain(index.files.iter().map(|f| &f.path)) {
+        check_path(path, false)?;
+    }
+    Ok(index)
+}
+
+/// Lists the backups in `dir`, newest first.
+pub fn get_backups(dir: impl AsRef<Path>) -> Vec<Backup> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let index = read_index(&path).ok()?;
+            Some(Backup { path, index })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.index.created));
+    backups
+}
+
+/// Restores a backup into `library` and registers its manifest again.
+///
+/// Every file is checked against the index while it is written, a failed
+/// restore removes what was written so far.
+pub fn restore_backup(
+    path: impl AsRef<Path>,
+    backup: impl AsRef<Path>,
+    library: impl AsRef<Path>,
+    mut progress: impl FnMut(Progress),
+) -> io::Result<Game> {
+    let backup = backup.as_ref();
+    let library = library.as_ref();
+    guard(WriteOp::RestoreBackup)?;
+
+    let index = read_index(backup)?;
+    let target = library
+        .join("steamapps")
+        .join("common")
+        .join(&index.installdir);
+    let manifest = manifest_path(library, index.appid);
+    if let Some(existing) = library_of(&path, index.appid) {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("Game is already installed in {}", existing.display()),
+        ));
+    }
+    if target.exists() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    info!("Restoring {} to {}", backup.display(), target.display());
+
+    let total = index.size();
+    let result = (|| {
+        fs::create_dir_all(&target)?;
+        for dir in &index.dirs {
+            fs::create_dir_all(target.join(dir))?;
+        }
+
+        let mut done = 0;
+        let mut on_copy = |n| {
+            done += n;
+            progress(Progress {
+                stage: Stage::Copying,
+                done,
+                total,
+            });
+        };
+        let mut input = ChunkReader {
+            dir: backup,
+            chunks: index.chunks.iter(),
+            current: None,
+        };
+        for entry in &index.files {
+            let crc = atomic::Writer::new(target.join(&entry.path))
+                .write_with(|out| copy_exact(&mut input, out, entry.size, &mut on_copy))?;
+            if crc != entry.crc {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Verification failed for {}", entry.path),
+                ));
+            }
+        }
+
+        atomic::write(&manifest, &index.manifest)
+    })();
+
+    if let Err(e) = result {
+        fs::remove_dir_all(&target).ok();
+        fs::remove_file(&manifest).ok();
+        return Err(e);
+    }
+    update_libraryfolders(path.as_ref(), index.appid, None, library, total);
+
+    Ok(Game {
+        appid: index.appid,
+        details: AppData {
+            name: index.name,
+            ..Default::default()
+        },
+        installed: true,
+        path: target.to_string_lossy().to_string(),
+        build_id: vdf::parse(&index.manifest)
+            .ok()
+            .and_then(|doc| doc.path(&["AppState", "buildid"])?.as_str()?.parse().ok())
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn chunks_roundtrip() {
+        let dir = TempDir::new("backup");
+
+        // Crosses the chunk size a few times, also within single writes
+        let data: Vec<u8> = (0..200_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        let mut out = ChunkWriter::new(dir.path(), 300_000);
+        out.write_all(&data[..1000]).unwrap();
+        out.write_all(&data[1000..]).unwrap();
+        let chunks = out.finish().unwrap();
+        assert_eq!(
+            chunks.iter().map(|c| c.size).collect::<Vec<_>>(),
+            [300_000, 300_000, 200_000]
+        );
+        assert_eq!(chunks[2].file, "0002.gz");
+
+        let mut input = ChunkReader {
+            dir: dir.path(),
+            chunks: chunks.iter(),
+            current: None,
+        };
+        let mut back = Vec::new();
+        input.read_to_end(&mut back).unwrap();
+        assert_eq!(back, data);
+    }
+
+    #[test]
+    fn rejects_unsafe_paths() {
+        assert!(check_path("Game", true).is_ok());
+        assert!(check_path("bin/game.exe", false).is_ok());
+        assert!(check_path("bin/game.exe", true).is_err());
+        assert!(check_path("../evil", false).is_err());
+        assert!(check_path("bin/../../evil", false).is_err());
+        assert!(check_path("/etc/passwd", false).is_err());
+        assert!(check_path("", false).is_err());
+    }
+}
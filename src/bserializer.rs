@@ -0,0 +1,375 @@
+//! # bserializer
+//!
+//! Binary encoding of the game list used by [`crate::db::LibraryDb`].
+//!
+//! Format v2, all numbers little endian:
+//!
+//! ```text
+//! magic "STGM" | version u16 | record count u32
+//! per record:  length u32 | crc32 of the payload u32 | payload
+//! payload:     appid u32 | fields
+//! per field:   tag u8 | length u32 | value
+//! ```
+//!
+//! Unknown tags are skipped so newer fields don't break older readers. A
+//! record with a bad checksum is dropped and the rest are still read, a
+//! truncated file keeps every complete record. Files without the magic are
+//! read as the v1 layout of `steam.bin` from 0.6.5 and older.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufWriter, Error, ErrorKind, Read, Write},
+};
+
+use log::warn;
+
+use crate::{AppData, Game};
+
+const MAGIC: &[u8; 4] = b"STGM";
+pub const FORMAT_VERSION: u16 = 2;
+
+/// Longest string field, anything longer is not a game.
+const MAX_FIELD: usize = 16 * 1024;
+const MAX_RECORD: usize = 64 * 1024;
+/// Largest file that is read at all.
+const MAX_FILE: u64 = 256 * 1024 * 1024;
+
+const TAG_INSTALLED: u8 = 1;
+const TAG_APP_TYPE: u8 = 2;
+const TAG_NAME: u8 = 3;
+const TAG_HEADER_IMAGE: u8 = 4;
+const TAG_PATH: u8 = 5;
+const TAG_BUILD_ID: u8 = 6;
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+/// Reads from a byte slice without ever going past its end.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32().ok_or_else(|| invalid("Truncated string"))? as usize;
+        if len > MAX_FIELD {
+            return Err(invalid(format!("String of {len} bytes is too long")));
+        }
+        let bytes = self.take(len).ok_or_else(|| invalid("Truncated string"))?;
+        String::from_utf8(bytes.to_vec()).map_err(|e| invalid(e.to_string()))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+fn field(payload: &mut Vec<u8>, tag: u8, value: &[u8]) -> io::Result<()> {
+    if value.len() > MAX_FIELD {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Field {tag} has {} bytes", value.len()),
+        ));
+    }
+    payload.push(tag);
+    payload.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    payload.extend_from_slice(value);
+    Ok(())
+}
+
+fn encode(game: &Game) -> io::Result<Vec<u8>> {
+    let mut payload = Vec::with_capacity(128);
+    payload.extend_from_slice(&game.appid.to_le_bytes());
+    field(&mut payload, TAG_INSTALLED, &[game.installed as u8])?;
+    field(&mut payload, TAG_APP_TYPE, game.details.app_type.as_bytes())?;
+    field(&mut payload, TAG_NAME, game.details.name.as_bytes())?;
+    field(
+        &mut payload,
+        TAG_HEADER_IMAGE,
+        game.details.header_image.as_bytes(),
+    )?;
+    field(&mut payload, TAG_PATH, game.path.as_bytes())?;
+    field(&mut payload, TAG_BUILD_ID, &game.build_id.to_le_bytes())?;
+    Ok(payload)
+}
+
+fn decode(payload: &[u8]) -> io::Result<Game> {
+    let mut bytes = Bytes(payload);
+    let appid = bytes.u32().ok_or_else(|| invalid("Record without appid"))?;
+    let mut game = Game {
+        appid,
+        ..Default::default()
+    };
+
+    while !bytes.is_empty() {
+        let (Some(tag), Some(len)) = (bytes.u8(), bytes.u32()) else {
+            return Err(invalid("Truncated field"));
+        };
+        let value = bytes
+            .take(len as usize)
+            .ok_or_else(|| invalid("Truncated field"))?;
+        let text = || String::from_utf8(value.to_vec()).map_err(|e| invalid(e.to_string()));
+
+        match tag {
+            TAG_INSTALLED => game.installed = value.first().is_some_and(|b| *b != 0),
+            TAG_APP_TYPE => game.details.app_type = text()?,
+            TAG_NAME => game.details.name = text()?,
+            TAG_HEADER_IMAGE => game.details.header_image = text()?,
+            TAG_PATH => game.path = text()?,
+            TAG_BUILD_ID => {
+                game.build_id = Bytes(value).u32().ok_or_else(|| invalid("Bad build id"))?
+            }
+            // Written by a newer version
+            _ => (),
+        }
+    }
+    Ok(game)
+}
+
+/// Games read from a file and what could not be read.
+#[derive(Debug, Default)]
+pub struct Decoded {
+    pub games: HashMap<u32, Game>,
+    /// Records dropped for a bad checksum or because the file ended early.
+    pub lost: usize,
+}
+
+pub struct GameMap;
+
+impl GameMap {
+    pub fn write_to(file: &mut impl Write, map: &HashMap<u32, Game>) -> io::Result<()> {
+        let mut writer = BufWriter::new(file);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(map.len() as u32).to_le_bytes())?;
+        for game in map.values() {
+            let payload = encode(game)?;
+            writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+            writer.write_all(&crc32fast::hash(&payload).to_le_bytes())?;
+            writer.write_all(&payload)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Reads the games, logging records that were lost, see [`GameMap::decode`].
+    pub fn read_from(file: &mut impl Read) -> io::Result<HashMap<u32, Game>> {
+        let mut data = Vec::new();
+        file.take(MAX_FILE + 1).read_to_end(&mut data)?;
+        if data.len() as u64 > MAX_FILE {
+            return Err(invalid("File is too large for a game list"));
+        }
+
+        let decoded = Self::decode(&data)?;
+        if decoded.lost > 0 {
+            warn!(
+                "Game list is damaged, recovered {} games and lost {}",
+                decoded.games.len(),
+                decoded.lost
+            );
+        }
+        Ok(decoded.games)
+    }
+
+    /// Decodes a whole file. Garbage is rejected, damaged or truncated
+    /// records are counted in [`Decoded::lost`].
+    pub fn decode(data: &[u8]) -> io::Result<Decoded> {
+        let mut bytes = Bytes(data);
+        if data.get(..4) != Some(MAGIC.as_slice()) {
+            return Self::decode_v1(bytes);
+        }
+        bytes.take(4);
+
+        let version = bytes.u16().ok_or_else(|| invalid("Truncated header"))?;
+        if version > FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("Game list format {version} is newer than {FORMAT_VERSION}"),
+            ));
+        }
+        let count = bytes.u32().ok_or_else(|| invalid("Truncated header"))? as usize;
+
+        let mut decoded = Decoded::default();
+        for read in 0..count {
+            let (Some(len), Some(crc)) = (bytes.u32(), bytes.u32()) else {
+                decoded.lost += count - read;
+                break;
+            };
+            let len = len as usize;
+            if len > MAX_RECORD {
+                // The length itself is broken, nothing after it can be found
+                decoded.lost += count - read;
+                break;
+            }
+            let Some(payload) = bytes.take(len) else {
+                decoded.lost += count - read;
+                break;
+            };
+
+            if crc32fast::hash(payload) != crc {
+                decoded.lost += 1;
+                continue;
+            }
+            match decode(payload) {
+                Ok(game) => {
+                    decoded.games.insert(game.appid, game);
+                }
+                Err(_) => decoded.lost += 1,
+            }
+        }
+
+        if decoded.games.is_empty() && decoded.lost > 0 {
+            return Err(invalid("No readable games in the game list"));
+        }
+        Ok(decoded)
+    }
+
+    /// The layout before v2: a count, then appid, installed and four strings
+    /// per game. It had no build id.
+    fn decode_v1(mut bytes: Bytes) -> io::Result<Decoded> {
+        let count = bytes.u32().ok_or_else(|| invalid("Empty game list"))? as usize;
+
+        let mut decoded = Decoded::default();
+        for read in 0..count {
+            let mut record = || -> io::Result<Game> {
+                let appid = bytes.u32().ok_or_else(|| invalid("Truncated"))?;
+                let installed = bytes.u8().ok_or_else(|| invalid("Truncated"))? != 0;
+                let app_type = bytes.string()?;
+                let name = bytes.string()?;
+                let header_image = bytes.string()?;
+                let path = bytes.string()?;
+                Ok(Game {
+                    appid,
+                    details: AppData {
+                        app_type,
+                        name,
+                        header_image,
+                    },
+                    installed,
+                    path,
+                    ..Default::default()
+                })
+            };
+
+            // Without record lengths there is no way to resync after an error
+            match record() {
+                Ok(game) => {
+                    decoded.games.insert(game.appid, game);
+                }
+                Err(_) => {
+                    decoded.lost += count - read;
+                    break;
+                }
+            }
+        }
+
+        if decoded.games.is_empty() && decoded.lost > 0 {
+            return Err(invalid("Not a game list"));
+        }
+        Ok(decoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::GameMap;
+    use crate::{AppData, Game};
+
+    fn games() -> HashMap<u32, Game> {
+        (1..=3)
+            .map(|appid| {
+                let game = Game {
+                    appid,
+                    details: AppData {
+                        name: format!("Game {appid}"),
+                        ..Default::default()
+                    },
+                    build_id: 40 + appid,
+                    ..Default::default()
+                };
+                (appid, game)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn write_read() {
+        let mut buf = Vec::new();
+        let mut map = HashMap::new();
+        let g = Game {
+            appid: 1,
+            build_id: 42,
+            ..Default::default()
+        };
+        map.insert(1, g);
+        GameMap::write_to(&mut buf, &map).unwrap();
+
+        let read = GameMap::read_from(&mut buf.as_slice()).unwrap();
+        assert_eq!(read[&1].build_id, 42);
+    }
+
+    #[test]
+    fn recover_damaged() {
+        let mut buf = Vec::new();
+        GameMap::write_to(&mut buf, &games()).unwrap();
+
+        // Cut into the last record
+        let decoded = GameMap::decode(&buf[..buf.len() - 5]).unwrap();
+        assert_eq!((decoded.games.len(), decoded.lost), (2, 1));
+
+        // Flip a byte in the first record's payload
+        let mut flipped = buf.clone();
+        flipped[10 + 8 + 2] ^= 0xff;
+        let decoded = GameMap::decode(&flipped).unwrap();
+        assert_eq!((decoded.games.len(), decoded.lost), (2, 1));
+
+        // Garbage claiming a huge count and lengths
+        let garbage = [0xffu8; 64];
+        assert!(GameMap::decode(&garbage).is_err());
+        let mut garbage = b"STGM\x02\x00".to_vec();
+        garbage.extend_from_slice(&[0xff; 32]);
+        assert!(GameMap::decode(&garbage).is_err());
+    }
+
+    #[test]
+    fn read_v1() {
+        let mut buf = 1u32.to_le_bytes().to_vec();
+        buf.extend_from_slice(&730u32.to_le_bytes());
+        buf.push(1);
+        for text in ["game", "Counter-Strike 2", "", "/games/cs2"] {
+            buf.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            buf.extend_from_slice(text.as_bytes());
+        }
+
+        let read = GameMap::read_from(&mut buf.as_slice()).unwrap();
+        let game = &read[&730];
+        assert!(game.installed);
+        assert_eq!(game.details.name, "Counter-Strike 2");
+        assert_eq!((game.path.as_str(), game.build_id), ("/games/cs2", 0));
+    }
+}
@@ -0,0 +1,200 @@
+//! # changes
+//!
+//! What changed in the game list between two fetches, see [`crate::get_games`].
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Game;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Installed,
+    Uninstalled,
+    Renamed { from: String },
+    BuildChanged { from: u32, to: u32 },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added => write!(f, "added"),
+            Change::Removed => write!(f, "removed"),
+            Change::Installed => write!(f, "installed"),
+            Change::Uninstalled => write!(f, "uninstalled"),
+            Change::Renamed { from } => write!(f, "renamed from {from}"),
+            Change::BuildChanged { from, to } => write!(f, "updated from build {from} to {to}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GameChange {
+    pub appid: u32,
+    pub name: String,
+    pub change: Change,
+}
+
+impl Display for GameChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name.is_empty() {
+            true => write!(f, "{} {}", self.appid, self.change),
+            false => write!(f, "{} ({}) {}", self.name, self.appid, self.change),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    /// Unix timestamp of the fetch.
+    pub time: u64,
+    pub changes: Vec<GameChange>,
+}
+
+impl Changes {
+    /// Compares two game lists, sorted by appid.
+    pub fn between(old: &HashMap<u32, Game>, new: &HashMap<u32, Game>) -> Self {
+        let mut changes = Vec::new();
+        let mut push = |game: &Game, change| {
+            changes.push(GameChange {
+                appid: game.appid,
+                name: game.details.name.clone(),
+                change,
+            })
+        };
+
+        for (appid, game) in new {
+            let Some(before) = old.get(appid) else {
+                push(game, Change::Added);
+                continue;
+            };
+
+            match (before.installed, game.installed) {
+                (false, true) => push(game, Change::Installed),
+                (true, false) => push(game, Change::Uninstalled),
+                _ => (),
+            }
+            if !before.details.name.is_empty() && before.details.name != game.details.name {
+                push(
+                    game,
+                    Change::Renamed {
+                        from: before.details.name.clone(),
+                    },
+                );
+            }
+            // 0 means the build is not known, e.g. the game was not installed
+            if before.build_id != 0 && game.build_id != 0 && before.build_id != game.build_id {
+                push(
+                    game,
+                    Change::BuildChanged {
+                        from: before.build_id,
+                        to: game.build_id,
+                    },
+                );
+            }
+        }
+
+        for (appid, game) in old {
+            if !new.contains_key(appid) {
+                push(game, Change::Removed);
+            }
+        }
+
+        changes.sort_by_key(|c| c.appid);
+        Self {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            changes,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Short summary like `2 added, 1 updated`.
+    pub fn summary(&self) -> String {
+        let count = |f: fn(&Change) -> bool| self.changes.iter().filter(|c| f(&c.change)).count();
+        [
+            (count(|c| *c == Change::Added), "added"),
+            (count(|c| *c == Change::Removed), "removed"),
+            (count(|c| *c == Change::Installed), "installed"),
+            (count(|c| *c == Change::Uninstalled), "uninstalled"),
+            (count(|c| matches!(c, Change::Renamed { .. })), "renamed"),
+            (
+                count(|c| matches!(c, Change::BuildChanged { .. })),
+                "updated",
+            ),
+        ]
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, what)| format!("{n} {what}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppData;
+
+    fn game(appid: u32, name: &str, installed: bool, build_id: u32) -> Game {
+        Game {
+            appid,
+            details: AppData {
+                name: name.to_string(),
+                ..Default::default()
+            },
+            installed,
+            build_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn diff_games() {
+        let old = HashMap::from([
+            (1, game(1, "Removed", false, 0)),
+            (2, game(2, "Old name", true, 10)),
+            (3, game(3, "Uninstalled", true, 5)),
+        ]);
+        let new = HashMap::from([
+            (2, game(2, "New name", true, 11)),
+            (3, game(3, "Uninstalled", false, 0)),
+            (4, game(4, "Added", true, 1)),
+        ]);
+
+        let changes = Changes::between(&old, &new);
+        let kinds: Vec<(u32, &Change)> = changes
+            .changes
+            .iter()
+            .map(|c| (c.appid, &c.change))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (1, &Change::Removed),
+                (
+                    2,
+                    &Change::Renamed {
+                        from: "Old name".to_string()
+                    }
+                ),
+                (2, &Change::BuildChanged { from: 10, to: 11 }),
+                (3, &Change::Uninstalled),
+                (4, &Change::Added),
+            ]
+        );
+        assert_eq!(
+            changes.summary(),
+            "1 added, 1 removed, 1 uninstalled, 1 renamed, 1 updated"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! # cleanup
+//!
+//! Finds data that can be deleted safely: game folders without a manifest,
+//! caches Steam rebuilds on its own, leftovers of downloads and artwork of
+//! games which are not in the library anymore.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::client::{WriteOp, guard};
+use crate::library::{install_dir, library_folders};
+use crate::paths;
+use crate::{Game, dir_size, format_size};
+
+/// Folders of Steamtools itself which keep one entry per appid.
+fn artwork_dirs() -> [PathBuf; 2] {
+    [paths::icons_dir(), paths::thumbnails_dir()]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Category {
+    /// Folder in `steamapps/common` no manifest points to.
+    OrphanedGame,
+    ShaderCache,
+    Downloading,
+    Temp,
+    /// Steamtools artwork of games no longer in the library.
+    Artwork,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::OrphanedGame,
+        Category::ShaderCache,
+        Category::Downloading,
+        Category::Temp,
+        Category::Artwork,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Category::OrphanedGame => "Game folders without a manifest",
+            Category::ShaderCache => "Shader caches (rebuilt by Steam)",
+            Category::Downloading => "Unfinished downloads",
+            Category::Temp => "Temporary files",
+            Category::Artwork => "Artwork of removed games",
+        }
+    }
+
+    /// Whether Steam could be using the files while it runs.
+    fn used_by_steam(&self) -> bool {
+        *self != Category::Artwork
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub category: Category,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Outcome of [`clean`].
+#[derive(Debug, Default)]
+pub struct Report {
+    pub dry_run: bool,
+    pub removed: Vec<Item>,
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Report {
+    pub fn freed(&self) -> u64 {
+        self.removed.iter().map(|i| i.size).sum()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.dry_run {
+            "Would delete"
+        } else {
+            "Deleted"
+        };
+        writeln!(
+            f,
+            "{verb} {} item(s), {}",
+            self.removed.len(),
+            format_size(self.freed())
+        )?;
+        for item in &self.removed {
+            writeln!(f, "  {} ({})", item.path.display(), format_size(item.size))?;
+        }
+        for (path, e) in &self.errors {
+            writeln!(f, "  Failed: {} ({e})", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+fn children(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+fn item(category: Category, path: PathBuf) -> Item {
+    let size = if path.is_dir() {
+        dir_size(&path)
+    } else {
+        fs::metadata(&path).map_or(0, |m| m.len())
+    };
+    Item {
+        category,
+        path,
+        size,
+    }
+}
+
+/// Scans every library folder and the Steamtools artwork. This measures a lot
+/// of folders so it should not run on the ui thread.
+pub fn scan(path: impl AsRef<Path>, games: &HashMap<u32, Game>) -> Vec<Item> {
+    let mut items = Vec::new();
+
+    for library in library_folders(path) {
+        let steamapps = library.join("steamapps");
+
+        let installdirs: HashSet<String> = children(&steamapps)
+            .iter()
+            .filter_map(|p| {
+                let name = p.file_name()?.to_str()?;
+                let appid = name
+                    .strip_prefix("appmanifest_")?
+                    .strip_suffix(".acf")?
+                    .parse::<u32>()
+                    .ok()?;
+                install_dir(&library, appid).ok()
+            })
+            .map(|dir| dir.to_lowercase())
+            .collect();
+
+        // Without manifests every folder would be reported, that is most
+        // likely a broken library rather than orphaned games
+        if !installdirs.is_empty() {
+            items.extend(
+                children(&steamapps.join("common"))
+                    .into_iter()
+                    .filter(|p| p.is_dir())
+                    .filter(|p| {
+                        p.file_name().is_some_and(|n| {
+                            !installdirs.contains(&n.to_string_lossy().to_lowercase())
+                        })
+                    })
+                    .map(|p| item(Category::OrphanedGame, p)),
+            );
+        }
+
+        for (dir, category) in [
+            ("shadercache", Category::ShaderCache),
+            ("downloading", Category::Downloading),
+            ("temp", Category::Temp),
+        ] {
+            items.extend(
+                children(&steamapps.join(dir))
+                    .into_iter()
+                    .map(|p| item(category, p)),
+            );
+        }
+    }
+
+    for dir in artwork_dirs() {
+        items.extend(
+            children(&dir)
+                .into_iter()
+                .filter(|p| {
+                    p.file_stem()
+                        .and_then(|s| s.to_str()?.parse::<u32>().ok())
+                        .is_some_and(|appid| !games.contains_key(&appid))
+                })
+                .map(|p| item(Category::Artwork, p)),
+        );
+    }
+
+    items.sort_by(|a, b| a.category.cmp(&b.category).then(b.size.cmp(&a.size)));
+    items
+}
+
+/// The [`WriteOp`] deleting `items` is.
+pub fn write_op(items: &[Item]) -> WriteOp {
+    WriteOp::Cleanup {
+        steam_files: items.iter().any(|i| i.category.used_by_steam()),
+    }
+}
+
+/// Deletes the given items, with `dry_run` nothing is touched and the report
+/// lists what would be deleted. Items Steam might use are refused while it runs.
+pub fn clean(items: &[Item], dry_run: bool) -> io::Result<Report> {
+    if !dry_run {
+        guard(write_op(items))?;
+    }
+
+    let mut report = Report {
+        dry_run,
+        ..Default::default()
+    };
+
+    for item in items {
+        let result = match (dry_run, item.path.is_dir()) {
+            (true, _) => Ok(()),
+            (false, true) => fs::remove_dir_all(&item.path),
+            (false, false) => fs::remove_file(&item.path),
+        };
+
+        match result {
+            Ok(()) => report.removed.push(item.clone()),
+            Err(e) => report.errors.push((item.path.clone(), e.to_string())),
+        }
+    }
+
+    Ok(report)
+}
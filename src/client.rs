@@ -0,0 +1,311 @@
+//! # client
+//!
+//! Starting and stopping the Steam client.
+
+use std::fmt::{self, Display};
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(not(target_os = "windows"))]
+use std::fs;
+
+use log::{info, warn};
+
+use crate::uri::SteamUri;
+
+/// How long [`SteamClient::restart`] waits for Steam to shut down by itself.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Running,
+    Stopped,
+}
+
+/// Operations writing to the Steam folder. Some files are only read by Steam
+/// on startup and written back on exit, changing them while Steam runs is
+/// either lost or confuses Steam.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOp {
+    /// `config/config.vdf`
+    CompatTool,
+    /// `userdata/<id>/config/localconfig.vdf`
+    LaunchOptions,
+    /// Manifests and game folders in `steamapps`
+    MoveGame,
+    CreateBackup,
+    RestoreBackup,
+    /// Removing leftovers, `steam_files` if any are inside `steamapps`.
+    Cleanup {
+        steam_files: bool,
+    },
+    /// Resetting or deleting a Proton prefix in `compatdata`
+    Prefix,
+    Screenshot,
+}
+
+impl WriteOp {
+    pub fn needs_steam_closed(&self) -> bool {
+        match self {
+            WriteOp::CompatTool
+            | WriteOp::LaunchOptions
+            | WriteOp::MoveGame
+            | WriteOp::RestoreBackup => true,
+            WriteOp::Cleanup { steam_files } => *steam_files,
+            WriteOp::CreateBackup | WriteOp::Prefix | WriteOp::Screenshot => false,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            WriteOp::CompatTool => "Changing the compatibility tool",
+            WriteOp::LaunchOptions => "Changing launch options",
+            WriteOp::MoveGame => "Moving a game",
+            WriteOp::CreateBackup => "Creating a backup",
+            WriteOp::RestoreBackup => "Restoring a backup",
+            WriteOp::Cleanup { .. } => "Cleaning up",
+            WriteOp::Prefix => "Changing a Proton prefix",
+            WriteOp::Screenshot => "Changing screenshots",
+        }
+    }
+}
+
+impl Display for WriteOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+/// Outcome of [`SteamClient::apply`].
+#[derive(Debug)]
+pub struct Applied<T> {
+    /// What the operation returned, an error if Steam could not be closed for it.
+    pub result: io::Result<T>,
+    /// Set if Steam was closed for the operation but could not be started again.
+    pub restart: Option<io::Error>,
+}
+
+/// Fails with [`ErrorKind::ResourceBusy`] if `op` needs Steam closed but it runs.
+/// Every writer calls this first, so nothing is written behind Steam's back.
+pub fn guard(op: WriteOp) -> io::Result<()> {
+    if op.needs_steam_closed() && steam_running() {
+        return Err(Error::new(
+            ErrorKind::ResourceBusy,
+            format!("{op} requires Steam to be closed"),
+        ));
+    }
+    Ok(())
+}
+
+/// Controls the Steam client installed at `path`.
+#[derive(Debug, Clone)]
+pub struct SteamClient {
+    pub path: PathBuf,
+}
+
+/// Checks if the Steam client is running.
+pub fn steam_running() -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("tasklist")
+            .args(["/FI", "IMAGENAME eq steam.exe", "/NH"])
+            .output()
+            .map(|o| String::from_utf8_lossy(&o.stdout).contains("steam.exe"))
+            .unwrap_or(false)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        !pids().is_empty()
+    }
+}
+
+/// Process ids of the Steam client.
+#[cfg(target_os = "macos")]
+fn pids() -> Vec<u32> {
+    Command::new("pgrep")
+        .args(["-x", "steam_osx"])
+        .output()
+        .map(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .lines()
+                .filter_map(|l| l.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Process ids of the Steam client.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn pids() -> Vec<u32> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            fs::read_to_string(e.path().join("comm")).is_ok_and(|comm| comm.trim() == "steam")
+        })
+        .filter_map(|e| e.file_name().to_str()?.parse().ok())
+        .collect()
+}
+
+/// Reaps a child in the background, Steam and its launchers are not waited on.
+fn detach(mut child: Child) {
+    thread::spawn(move || child.wait());
+}
+
+/// Looks up a program in `PATH`.
+#[cfg(not(target_os = "windows"))]
+fn which(program: &str) -> Option<PathBuf> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|p| p.is_file())
+    })
+}
+
+impl SteamClient {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// The executable that accepts `-shutdown`, if there is one.
+    fn binary(&self) -> Option<PathBuf> {
+        #[cfg(target_os = "windows")]
+        {
+            Some(self.path.join("steam.exe")).filter(|p| p.is_file())
+        }
+        #[cfg(not(target_os = "windows"))]
+        {
+            Some(self.path.join("steam.sh"))
+                .filter(|p| p.is_file())
+                .or_else(|| which("steam"))
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        if steam_running() {
+            Status::Running
+        } else {
+            Status::Stopped
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status() == Status::Running
+    }
+
+    pub fn start(&self) -> io::Result<()> {
+        info!("Starting Steam");
+        match self.binary() {
+            Some(binary) => Command::new(binary).spawn().map(detach),
+            None => SteamUri::Main.open(),
+        }
+    }
+
+    /// Asks Steam to shut down, it may take a while until it actually exits.
+    pub fn stop(&self) -> io::Result<()> {
+        info!("Stopping Steam");
+        match self.binary() {
+            Some(binary) => Command::new(binary).arg("-shutdown").spawn().map(detach),
+            None => SteamUri::Exit.open(),
+        }
+    }
+
+    /// Kills Steam without letting it save anything.
+    pub fn kill(&self) -> io::Result<()> {
+        warn!("Killing Steam");
+        #[cfg(target_os = "windows")]
+        let status = Command::new("taskkill")
+            .args(["/F", "/IM", "steam.exe"])
+            .status()?;
+        #[cfg(not(target_os = "windows"))]
+        let status = {
+            let pids = pids();
+            if pids.is_empty() {
+                return Ok(());
+            }
+            Command::new("kill")
+                .arg("-9")
+                .args(pids.iter().map(u32::to_string))
+                .status()?
+        };
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::other(format!("Killing Steam failed: {status}")))
+        }
+    }
+
+    /// Blocks until Steam is not running anymore, false if it still runs
+    /// after `timeout`.
+    pub fn wait_until_exited(&self, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while self.is_running() {
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        true
+    }
+
+    /// Runs `op`, closing Steam before and starting it again afterwards if it
+    /// runs and `op` needs it closed. This blocks, run it on a thread.
+    ///
+    /// A failed restart doesn't undo the operation, so it is reported apart
+    /// from its result.
+    pub fn apply<T>(&self, op: WriteOp, f: impl FnOnce() -> io::Result<T>) -> Applied<T> {
+        if !op.needs_steam_closed() || !self.is_running() {
+            return Applied {
+                result: f(),
+                restart: None,
+            };
+        }
+
+        info!("Closing Steam for: {op}");
+        let stopped = self.stop().and_then(|()| {
+            if self.wait_until_exited(SHUTDOWN_TIMEOUT) {
+                Ok(())
+            } else {
+                Err(Error::new(ErrorKind::TimedOut, "Steam did not exit"))
+            }
+        });
+        if let Err(e) = stopped {
+            return Applied {
+                result: Err(e),
+                restart: None,
+            };
+        }
+
+        let result = f();
+        // Start again even if `op` failed, the user had it running
+        let restart = self.start().err();
+        if let Some(e) = &restart {
+            warn!("Starting Steam again after {op}: {e}");
+        }
+        Applied { result, restart }
+    }
+
+    /// Stops Steam and starts it again. If it does not exit within
+    /// [`SHUTDOWN_TIMEOUT`] it is killed. This blocks, run it on a thread.
+    pub fn restart(&self) -> io::Result<()> {
+        if self.is_running() {
+            self.stop()?;
+            if !self.wait_until_exited(SHUTDOWN_TIMEOUT) {
+                self.kill()?;
+                if !self.wait_until_exited(Duration::from_secs(5)) {
+                    return Err(Error::new(ErrorKind::TimedOut, "Steam did not exit"));
+                }
+            }
+        }
+        self.start()
+    }
+}
@@ -0,0 +1,204 @@
+//! # collections
+//!
+//! The collections of the Steam library, stored in
+//! `userdata/<accountid>/config/cloudstorage/cloud-storage-namespace-1.json`.
+//!
+//! The file is a list of `[key, entry]` pairs, every collection is an entry
+//! with a key starting with `user-collections.` and its data as a JSON string
+//! in `value`.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::userdata::{active_user, user_dir};
+
+const KEY_PREFIX: &str = "user-collections.";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    /// Apps added by hand, for dynamic collections these are always shown.
+    pub added: HashSet<u32>,
+    pub removed: HashSet<u32>,
+    /// Dynamic collections only, matched against the game name.
+    pub search: Option<String>,
+    pub dynamic: bool,
+    /// Set for dynamic collections using filters other than the name search.
+    /// Those can't be evaluated, so only the apps added by hand are shown.
+    pub partial: bool,
+    /// Apps in the Hidden collection, Steam leaves them out of all others.
+    pub hidden: HashSet<u32>,
+}
+
+impl Collection {
+    pub fn contains(&self, appid: u32, name: &str) -> bool {
+        if self.removed.contains(&appid) || self.hidden.contains(&appid) {
+            return false;
+        }
+        if self.added.contains(&appid) {
+            return true;
+        }
+        self.dynamic
+            && !self.partial
+            && self
+                .search
+                .as_ref()
+                .is_none_or(|s| name.to_lowercase().contains(&s.to_lowercase()))
+    }
+}
+
+pub fn collections_path(path: impl AsRef<Path>, account: u32) -> PathBuf {
+    user_dir(path, account)
+        .join("config")
+        .join("cloudstorage")
+        .join("cloud-storage-namespace-1.json")
+}
+
+fn appids(value: Option<&Value>) -> HashSet<u32> {
+    value
+        .and_then(Value::as_array)
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| u32::try_from(id.as_u64()?).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_collection(key: &str, entry: &Value) -> Option<Collection> {
+    let id = key.strip_prefix(KEY_PREFIX)?;
+    if entry.get("is_deleted").and_then(Value::as_bool) == Some(true) {
+        return None;
+    }
+    let value: Value = serde_json::from_str(entry.get("value")?.as_str()?).ok()?;
+
+    let name = match id {
+        "favorite" => "Favorites".to_string(),
+        "hidden" => "Hidden".to_string(),
+        _ => value.get("name")?.as_str()?.to_string(),
+    };
+
+    let mut collection = Collection {
+        id: id.to_string(),
+        name,
+        added: appids(value.get("added")),
+        removed: appids(value.get("removed")),
+        ..Default::default()
+    };
+
+    if let Some(spec) = value.get("filterSpec") {
+        collection.dynamic = true;
+        collection.search = spec
+            .get("strSearchText")
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        collection.partial = spec
+            .get("filterGroups")
+            .and_then(Value::as_array)
+            .is_some_and(|groups| {
+                groups.iter().any(|g| {
+                    g.get("rgOptions")
+                        .and_then(Value::as_array)
+                        .is_some_and(|o| !o.is_empty())
+                })
+            });
+    }
+
+    Some(collection)
+}
+
+/// Parses the contents of a cloud storage namespace file, sorted by name with
+/// Favorites first and Hidden last.
+pub fn parse(text: &str) -> io::Result<Vec<Collection>> {
+    let root: Value =
+        serde_json::from_str(text).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let entries = root
+        .as_array()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Expected a list"))?;
+
+    let mut collections: Vec<Collection> = entries
+        .iter()
+        .filter_map(|pair| {
+            let key = pair.get(0)?.as_str()?;
+            parse_collection(key, pair.get(1)?)
+        })
+        .collect();
+
+    let hidden = collections
+        .iter()
+        .find(|c| c.id == "hidden")
+        .map(|c| c.added.clone())
+        .unwrap_or_default();
+    for collection in collections.iter_mut().filter(|c| c.id != "hidden") {
+        collection.hidden = hidden.clone();
+    }
+
+    collections.sort_by_cached_key(|c| {
+        let order = match c.id.as_str() {
+            "favorite" => 0,
+            "hidden" => 2,
+            _ => 1,
+        };
+        (order, c.name.to_lowercase())
+    });
+    Ok(collections)
+}
+
+/// Reads the collections of the active account, empty if there are none.
+pub fn get_collections(path: impl AsRef<Path>) -> Vec<Collection> {
+    let Some(account) = active_user(&path) else {
+        return Vec::new();
+    };
+    fs::read_to_string(collections_path(&path, account))
+        .and_then(|text| parse(&text))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_namespace() {
+        let text = r#"[
+            ["user-collections.favorite", {"key": "user-collections.favorite", "timestamp": 1, "value": "{\"id\":\"favorite\",\"added\":[70],\"removed\":[]}", "version": "2"}],
+            ["user-collections.uc-abc", {"key": "user-collections.uc-abc", "timestamp": 1, "value": "{\"id\":\"uc-abc\",\"name\":\"Shooters\",\"added\":[10,240],\"removed\":[]}", "version": "3"}],
+            ["user-collections.uc-dyn", {"key": "user-collections.uc-dyn", "timestamp": 1, "value": "{\"id\":\"uc-dyn\",\"name\":\"Portal\",\"added\":[],\"removed\":[620],\"filterSpec\":{\"nFormatVersion\":2,\"strSearchText\":\"portal\",\"filterGroups\":[{\"rgOptions\":[],\"bAcceptUnion\":false}]}}", "version": "1"}],
+            ["user-collections.hidden", {"key": "user-collections.hidden", "timestamp": 1, "value": "{\"id\":\"hidden\",\"added\":[10],\"removed\":[]}", "version": "1"}],
+            ["user-collections.uc-tags", {"key": "user-collections.uc-tags", "timestamp": 1, "value": "{\"id\":\"uc-tags\",\"name\":\"Tagged\",\"added\":[70],\"removed\":[],\"filterSpec\":{\"nFormatVersion\":2,\"strSearchText\":\"\",\"filterGroups\":[{\"rgOptions\":[4],\"bAcceptUnion\":false}]}}", "version": "1"}],
+            ["user-collections.uc-old", {"key": "user-collections.uc-old", "timestamp": 1, "is_deleted": true, "version": "4"}],
+            ["showcases.1", {"key": "showcases.1", "timestamp": 1, "value": "{}", "version": "1"}]
+        ]"#;
+
+        let collections = parse(text).unwrap();
+        let names: Vec<&str> = collections.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Favorites", "Portal", "Shooters", "Tagged", "Hidden"]
+        );
+
+        assert!(collections[2].contains(240, "Counter-Strike: Source"));
+        assert!(!collections[2].contains(400, "Portal"));
+        // Hidden apps only show up in Hidden
+        assert!(!collections[2].contains(10, "Counter-Strike"));
+        assert!(collections[4].contains(10, "Counter-Strike"));
+
+        let dynamic = &collections[1];
+        assert!(dynamic.dynamic && !dynamic.partial);
+        assert!(dynamic.contains(400, "Portal"));
+        assert!(!dynamic.contains(620, "Portal 2"));
+        assert!(!dynamic.contains(10, "Counter-Strike"));
+
+        // Unknown filters only keep what was added by hand
+        let partial = &collections[3];
+        assert!(partial.dynamic && partial.partial);
+        assert!(partial.contains(70, "Half-Life"));
+        assert!(!partial.contains(400, "Portal"));
+    }
+}
@@ -0,0 +1,389 @@
+//! # db
+//!
+//! The game library on disk, shared by the GUI and headless tools. It lives
+//! at [`crate::paths::library_path`].
+//!
+//! The file starts with the schema version (`u32`, little endian) followed by
+//! the games encoded with [`GameMap`]. Changes go through
+//! [`LibraryDb::transaction`], which only touches the file and the loaded
+//! games if the whole transaction succeeded. The previous file is kept as a
+//! backup and read instead if the library is damaged.
+//!
+//! Threads share the library through [`SharedLibrary`]: readers get an
+//! immutable [`Snapshot`] without locking, writers publish a new one with the
+//! next [`Snapshot::generation`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+use log::info;
+
+use crate::Game;
+use crate::atomic;
+use crate::bserializer::GameMap;
+
+/// Bumped whenever the stored games change meaning.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Default)]
+pub struct LibraryDb {
+    path: PathBuf,
+    /// Shared with the [`Snapshot`]s, only copied by a transaction.
+    games: Arc<HashMap<u32, Game>>,
+    /// Lowercase names to appids.
+    names: BTreeMap<String, Vec<u32>>,
+}
+
+/// Pending changes, see [`LibraryDb::transaction`].
+#[derive(Debug)]
+pub struct Transaction {
+    games: HashMap<u32, Game>,
+}
+
+impl Transaction {
+    pub fn get(&self, appid: u32) -> Option<&Game> {
+        self.games.get(&appid)
+    }
+
+    pub fn get_mut(&mut self, appid: u32) -> Option<&mut Game> {
+        self.games.get_mut(&appid)
+    }
+
+    pub fn insert(&mut self, game: Game) -> Option<Game> {
+        self.games.insert(game.appid, game)
+    }
+
+    pub fn remove(&mut self, appid: u32) -> Option<Game> {
+        self.games.remove(&appid)
+    }
+
+    /// Replaces every game, e.g. with the result of [`crate::get_games`].
+    pub fn replace(&mut self, games: HashMap<u32, Game>) {
+        self.games = games;
+    }
+
+    /// Applies a list fetched from the `before` snapshot, keeping what other
+    /// transactions changed since. Details always come from `fetched`, the
+    /// install state, path and build only where they are still as in
+    /// `before`, so e.g. a game moved in the meantime keeps its new path.
+    pub fn merge(&mut self, before: &HashMap<u32, Game>, fetched: HashMap<u32, Game>) {
+        // Gone from the fetch, unless they were added after the snapshot
+        for appid in before.keys() {
+            if !fetched.contains_key(appid) {
+                self.games.remove(appid);
+            }
+        }
+
+        for (appid, game) in fetched {
+            let Some(current) = self.games.get_mut(&appid) else {
+                // Removed since the snapshot
+                if !before.contains_key(&appid) {
+                    self.games.insert(appid, game);
+                }
+                continue;
+            };
+
+            let unchanged = before.get(&appid).is_none_or(|old| {
+                old.installed == current.installed
+                    && old.path == current.path
+                    && old.build_id == current.build_id
+            });
+            current.details = game.details;
+            if unchanged {
+                current.installed = game.installed;
+                current.path = game.path;
+                current.build_id = game.build_id;
+            }
+        }
+    }
+}
+
+impl LibraryDb {
+    /// Opens the database, a missing file is an empty library.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let parse = |data: &[u8]| {
+            let Some((version, mut games)) = data.split_first_chunk::<4>() else {
+                return Err(Error::from(ErrorKind::UnexpectedEof));
+            };
+            let version = u32::from_le_bytes(*version);
+            if version > SCHEMA_VERSION {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "{} has schema {version}, this version of Steamtools reads up to {SCHEMA_VERSION}",
+                        path.display()
+                    ),
+                ));
+            }
+            GameMap::read_from(&mut games)
+        };
+        let games = match atomic::read_with_backup(&path, parse) {
+            Ok(games) => games,
+            Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        let mut db = Self {
+            path,
+            games: Arc::new(games),
+            names: BTreeMap::new(),
+        };
+        db.index();
+        Ok(db)
+    }
+
+    fn index(&mut self) {
+        self.names.clear();
+        for game in self.games.values() {
+            self.names
+                .entry(game.details.name.to_lowercase())
+                .or_default()
+                .push(game.appid);
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn games(&self) -> &HashMap<u32, Game> {
+        &self.games
+    }
+
+    pub fn get(&self, appid: u32) -> Option<&Game> {
+        self.games.get(&appid)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Games with exactly this name, ignoring case.
+    pub fn by_name(&self, name: &str) -> Vec<&Game> {
+        self.names
+            .get(&name.to_lowercase())
+            .into_iter()
+            .flatten()
+            .filter_map(|appid| self.games.get(appid))
+            .collect()
+    }
+
+    /// Games whose name starts with `prefix` ignoring case, sorted by name.
+    pub fn with_prefix(&self, prefix: &str) -> Vec<&Game> {
+        let prefix = prefix.to_lowercase();
+        self.names
+            .range(prefix.clone()..)
+            .take_while(|(name, _)| name.starts_with(&prefix))
+            .flat_map(|(_, appids)| appids)
+            .filter_map(|appid| self.games.get(appid))
+            .collect()
+    }
+
+    /// Runs `f` on a copy of the games and writes the result if it returned
+    /// Ok. On any error the file and the loaded games stay as they were.
+    pub fn transaction<T>(
+        &mut self,
+        f: impl FnOnce(&mut Transaction) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut tx = Transaction {
+            games: (*self.games).clone(),
+        };
+        let result = f(&mut tx)?;
+
+        self.write(&tx.games)?;
+        self.games = Arc::new(tx.games);
+        self.index();
+        Ok(result)
+    }
+
+    /// Written atomically so a crash never leaves half a library.
+    fn write(&self, games: &HashMap<u32, Game>) -> io::Result<()> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        atomic::Writer::new(&self.path)
+            .backup(true)
+            .write_with(|file| {
+                file.write_all(&SCHEMA_VERSION.to_le_bytes())?;
+                GameMap::write_to(file, games)
+            })?;
+
+        info!("Saved {} games to {}", games.len(), self.path.display());
+        Ok(())
+    }
+}
+
+/// The games at one point in time, never changes once published.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    /// Increases with every published change, cheap to compare for views
+    /// that only rebuild when the library changed.
+    pub generation: u64,
+    games: Arc<HashMap<u32, Game>>,
+}
+
+impl Snapshot {
+    pub fn games(&self) -> &HashMap<u32, Game> {
+        &self.games
+    }
+
+    pub fn get(&self, appid: u32) -> Option<&Game> {
+        self.games.get(&appid)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+}
+
+/// A [`LibraryDb`] shared between threads. Reading is lock free, only
+/// transactions wait for each other.
+#[derive(Debug, Default)]
+pub struct SharedLibrary {
+    db: Mutex<LibraryDb>,
+    current: ArcSwap<Snapshot>,
+}
+
+impl SharedLibrary {
+    pub fn new(db: LibraryDb) -> Self {
+        let current = ArcSwap::from_pointee(Snapshot {
+            generation: 1,
+            games: db.games.clone(),
+        });
+        Self {
+            db: Mutex::new(db),
+            current,
+        }
+    }
+
+    /// The latest games, stays valid while newer ones are published.
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.current.load_full()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.current.load().generation
+    }
+
+    /// Runs a [`LibraryDb::transaction`] and publishes the result.
+    pub fn transaction<T>(
+        &self,
+        f: impl FnOnce(&mut Transaction) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut db = self.db.lock().unwrap();
+        let result = db.transaction(f)?;
+        self.current.store(Arc::new(Snapshot {
+            generation: self.generation() + 1,
+            games: db.games.clone(),
+        }));
+        Ok(result)
+    }
+
+    /// Runs `f` on the database, e.g. for the name index.
+    pub fn with_db<T>(&self, f: impl FnOnce(&LibraryDb) -> T) -> T {
+        f(&self.db.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppData;
+    use crate::test_util::TempDir;
+
+    fn game(appid: u32, name: &str) -> Game {
+        Game {
+            appid,
+            details: AppData {
+                name: name.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn transactions() {
+        let dir = TempDir::new("db");
+        let path = dir.join("library.db");
+        let mut db = LibraryDb::open(&path).unwrap();
+        assert!(db.is_empty());
+
+        db.transaction(|tx| {
+            tx.insert(game(10, "Portal"));
+            tx.insert(game(20, "Portal 2"));
+            tx.insert(game(30, "Half-Life"));
+            Ok(())
+        })
+        .unwrap();
+
+        // A failed transaction changes nothing
+        let failed = db.transaction(|tx| {
+            tx.remove(10);
+            Err::<(), _>(Error::other("cancelled"))
+        });
+        assert!(failed.is_err());
+        assert!(db.get(10).is_some());
+
+        let db = LibraryDb::open(&path).unwrap();
+        assert_eq!(db.games().len(), 3);
+        assert_eq!(db.by_name("portal")[0].appid, 10);
+        let prefix: Vec<u32> = db.with_prefix("por").iter().map(|g| g.appid).collect();
+        assert_eq!(prefix, [10, 20]);
+
+        // Snapshots stay as they were while new ones are published
+        let shared = SharedLibrary::new(db);
+        let before = shared.snapshot();
+        shared
+            .transaction(|tx| {
+                tx.remove(30);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(before.games().len(), 3);
+        assert_eq!(shared.snapshot().games().len(), 2);
+        assert_eq!(shared.generation(), before.generation + 1);
+        // Published without another copy
+        assert!(Arc::ptr_eq(
+            &shared.snapshot().games,
+            &shared.db.lock().unwrap().games
+        ));
+    }
+
+    #[test]
+    fn merge_keeps_newer_changes() {
+        let before: HashMap<u32, Game> = [game(10, "Portal"), game(20, "Portal 2")]
+            .into_iter()
+            .map(|g| (g.appid, g))
+            .collect();
+        let mut tx = Transaction {
+            games: before.clone(),
+        };
+        // Committed while the fetch ran
+        tx.get_mut(10).unwrap().path = "/mnt/games/Portal".to_string();
+        tx.insert(game(40, "Added"));
+
+        let mut fetched = HashMap::new();
+        for (appid, name) in [(10, "Portal (2007)"), (30, "Half-Life")] {
+            let mut game = game(appid, name);
+            game.path = "/old".to_string();
+            fetched.insert(appid, game);
+        }
+        tx.merge(&before, fetched);
+
+        let portal = tx.get(10).unwrap();
+        assert_eq!(portal.details.name, "Portal (2007)");
+        assert_eq!(portal.path, "/mnt/games/Portal");
+        assert!(tx.get(20).is_none());
+        assert_eq!(tx.get(30).unwrap().path, "/old");
+        assert!(tx.get(40).is_some());
+    }
+}
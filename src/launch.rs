@@ -0,0 +1,217 @@
+//! # launch
+//!
+//! Starts games through Steam with user Lua scripts running before the launch
+//! and after the game exited. Hook output and exit codes are appended to a log
+//! per game.
+//!
+//! Hooks get the globals `appid`, `name`, `path` and `phase` (`"pre"` or
+//! `"post"`), post hooks also `playtime` in seconds. A hook may `return` a
+//! number as its exit code, anything but 0 counts as failed.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::atomic;
+use crate::paths;
+use crate::sessions::running_games;
+#[cfg(feature = "lua")]
+use crate::st::run_hook_file;
+use crate::uri::SteamUri;
+use crate::{Game, format_timestamp};
+
+/// How long to wait for the game process to show up after asking Steam.
+const START_TIMEOUT: Duration = Duration::from_secs(120);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Hooks {
+    pub pre: Vec<PathBuf>,
+    pub post: Vec<PathBuf>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Pre,
+    Post,
+}
+
+impl Phase {
+    fn name(&self) -> &'static str {
+        match self {
+            Phase::Pre => "pre",
+            Phase::Post => "post",
+        }
+    }
+}
+
+/// Reads the hooks of every game, empty if there are none yet.
+pub fn load_hooks(path: impl AsRef<Path>) -> HashMap<u32, Hooks> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_hooks(path: impl AsRef<Path>, hooks: &HashMap<u32, Hooks>) -> io::Result<()> {
+    // Games without hooks are not worth keeping
+    let hooks: HashMap<&u32, &Hooks> = hooks.iter().filter(|(_, h)| !h.is_empty()).collect();
+    let json =
+        serde_json::to_string_pretty(&hooks).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    atomic::Writer::new(path).backup(true).write(json)
+}
+
+pub fn log_path(appid: u32) -> PathBuf {
+    paths::game_logs_dir().join(format!("{appid}.log"))
+}
+
+struct GameLog(File);
+
+impl GameLog {
+    fn open(appid: u32) -> io::Result<Self> {
+        fs::create_dir_all(paths::game_logs_dir())?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path(appid))
+            .map(Self)
+    }
+
+    fn line(&mut self, msg: impl AsRef<str>) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        if let Err(e) = writeln!(self.0, "[{}] {}", format_timestamp(now), msg.as_ref()) {
+            warn!("Writing game log: {e}");
+        }
+    }
+}
+
+/// Runs the scripts of one phase in order, stops at the first failing one.
+fn run_hooks(
+    game: &Game,
+    phase: Phase,
+    scripts: &[PathBuf],
+    extra: &[(&str, String)],
+    cancel: &Arc<AtomicBool>,
+    log: &mut GameLog,
+) -> bool {
+    let mut globals = vec![
+        ("appid", game.appid.to_string()),
+        ("name", game.details.name.clone()),
+        ("path", game.path.clone()),
+        ("phase", phase.name().to_string()),
+    ];
+    globals.extend(extra.iter().cloned());
+
+    for script in scripts {
+        if cancel.load(Ordering::Relaxed) {
+            return false;
+        }
+        log.line(format!(
+            "Running {} hook {}",
+            phase.name(),
+            script.display()
+        ));
+        #[cfg(feature = "lua")]
+        let (code, output) = run_hook_file(script.to_string_lossy().as_bytes(), &globals, cancel);
+        #[cfg(not(feature = "lua"))]
+        let (code, output) = (-1, "Built without the lua feature".to_string());
+        for line in output.lines() {
+            log.line(format!("  {line}"));
+        }
+        log.line(format!("{} exited with code {code}", script.display()));
+        if code != 0 {
+            return false;
+        }
+    }
+    true
+}
+
+fn is_running(game: &Game) -> bool {
+    running_games(&HashMap::from([(game.appid, game.clone())])).contains(&game.appid)
+}
+
+fn cancelled(cancel: &Arc<AtomicBool>, log: &mut GameLog) -> io::Result<()> {
+    if !cancel.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    log.line("Launch cancelled");
+    Err(Error::new(ErrorKind::Interrupted, "Launch cancelled"))
+}
+
+/// Launches a game through Steam and blocks until it exited, run it on a thread.
+///
+/// The game is found by its processes (see [`running_games`]). If it doesn't
+/// show up within [`START_TIMEOUT`] the post hooks are skipped, running them
+/// while the game may still run would be worse than not running them.
+///
+/// Setting `cancel` stops a running hook and the waiting, the game itself
+/// keeps running and the remaining hooks are skipped.
+pub fn launch(game: &Game, hooks: &Hooks, cancel: &Arc<AtomicBool>) -> io::Result<()> {
+    let mut log = GameLog::open(game.appid)?;
+    log.line(format!("Launching {} ({})", game.details.name, game.appid));
+
+    if !run_hooks(game, Phase::Pre, &hooks.pre, &[], cancel, &mut log) {
+        cancelled(cancel, &mut log)?;
+        log.line("Launch cancelled, a pre hook failed");
+        return Err(Error::other(format!(
+            "A pre launch hook failed, see {}",
+            log_path(game.appid).display()
+        )));
+    }
+
+    SteamUri::Run(game.appid).open()?;
+
+    let waiting = Instant::now();
+    while !is_running(game) && waiting.elapsed() < START_TIMEOUT {
+        cancelled(cancel, &mut log)?;
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    if !is_running(game) {
+        log.line("Could not detect the game, the post hooks are skipped");
+        return Err(Error::new(
+            ErrorKind::TimedOut,
+            format!(
+                "Could not detect the game, the post hooks were skipped. See {}",
+                log_path(game.appid).display()
+            ),
+        ));
+    }
+
+    let started = Instant::now();
+    log.line("Game started");
+    while is_running(game) {
+        cancelled(cancel, &mut log)?;
+        thread::sleep(POLL_INTERVAL);
+    }
+    log.line(format!(
+        "Game exited after {}s, the exit code is not available through Steam",
+        started.elapsed().as_secs()
+    ));
+
+    let playtime = [("playtime", started.elapsed().as_secs().to_string())];
+    if !run_hooks(game, Phase::Post, &hooks.post, &playtime, cancel, &mut log) {
+        cancelled(cancel, &mut log)?;
+        return Err(Error::other(format!(
+            "A post launch hook failed, see {}",
+            log_path(game.appid).display()
+        )));
+    }
+    Ok(())
+}
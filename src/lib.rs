@@ -1,16 +1,53 @@
-use log::{debug, error, info};
+// Only `get_games` and `install_melonloader` log here
+#[cfg(feature = "net")]
+use log::{debug, error, info, warn};
+#[cfg(feature = "net")]
 use reqwest::blocking;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::{self, DirBuilder, File};
-use std::io::Write;
+#[cfg(feature = "net")]
+use std::collections::{HashMap, HashSet};
+use std::fs;
+#[cfg(feature = "net")]
+use std::fs::DirBuilder;
+#[cfg(feature = "net")]
+use std::io;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+
+#[cfg(feature = "net")]
+use crate::changes::Changes;
 // use crate::st::{Lua, init_lua};
 
 // importing x32 mod
+#[cfg(feature = "lua")]
 pub mod st;
 
+pub mod atomic;
+pub mod backup;
+mod bserializer;
+pub mod changes;
+pub mod cleanup;
+pub mod client;
+pub mod collections;
+pub mod db;
+pub mod launch;
+pub mod library;
+pub mod localconfig;
+pub mod migrate;
+pub mod paths;
+pub mod proton;
+pub mod screenshots;
+pub mod sessions;
+#[cfg(test)]
+mod test_util;
+pub mod transfer;
+pub mod uri;
+pub mod userdata;
+pub mod vdf;
+pub mod workshop;
+
+pub use client::steam_running;
+
 // Can get ip timeouted if user requests too much !!!
 pub const STEAM_URL: &str = "https://store.steampowered.com/api/appdetails?appids=";
 
@@ -20,6 +57,7 @@ pub const STEAM_URL: &str = "https://store.steampowered.com/api/appdetails?appid
 pub const STEAM_APPLIST_URL: &str =
     "https://api.steampowered.com/IStoreService/GetAppList/v1/?key=";
 
+#[cfg(all(feature = "mods", target_os = "windows"))]
 const MELONLOADER_URL: &str = "https://github.com/LavaGang/MelonLoader/releases/download/v0.7.1/";
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -43,6 +81,9 @@ pub struct Game {
     pub details: AppData,
     pub installed: bool,
     pub path: String,
+    /// Installed build from the app manifest, 0 if not installed.
+    #[serde(default)]
+    pub build_id: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -53,67 +94,55 @@ pub struct Steam {
     pub melon_loader: bool,
 }
 
-pub fn install_melonloader(path: &str, melon_loader: bool) -> Option<()> {
+/// Starts the MelonLoader installer (downloading it first) if `melon_loader`
+/// is set and copies the local mod named after the game folder into `path`.
+#[cfg(feature = "mods")]
+pub fn install_melonloader(path: &str, melon_loader: bool) -> io::Result<()> {
+    let loader = paths::melonloader_dir().join("Loader.exe");
     if melon_loader {
-        if !Path::new("MelonLoader").exists() {
-            if let Err(e) = DirBuilder::new().create("MelonLoader") {
-                rfd::MessageDialog::new()
-                    .set_level(rfd::MessageLevel::Error)
-                    .set_buttons(rfd::MessageButtons::Ok)
-                    .set_description(e.to_string())
-                    .set_title("Error")
-                    .show();
-                return None;
-            }
+        if !paths::melonloader_dir().exists() {
+            DirBuilder::new().create(paths::melonloader_dir())?;
             #[cfg(target_os = "windows")]
             {
-                std::thread::spawn(|| {
+                let loader = loader.clone();
+                std::thread::spawn(move || {
                     let bytes =
                         blocking::get(format!("{}MelonLoader.Installer.exe", MELONLOADER_URL))
                             .ok()
                             .unwrap()
                             .bytes()
                             .unwrap_or_default();
-                    let mut file = File::create("MelonLoader/Loader.exe").unwrap();
-                    file.write_all(&bytes).unwrap();
-                    file.flush().unwrap();
+                    atomic::write(&loader, &bytes).unwrap();
                     Command::new("cmd")
-                        .args(["/C", ".\\MelonLoader\\Loader.exe"])
+                        .arg("/C")
+                        .arg(&loader)
                         .spawn()
                         .expect("Failed to open MelonLoader.");
                 });
             };
         } else {
-            Command::new("cmd")
-                .args(["/C", ".\\MelonLoader\\Loader.exe"])
-                .spawn()
-                .expect("Failed to open MelonLoader.");
+            Command::new("cmd").arg("/C").arg(&loader).spawn()?;
         }
     }
 
     let mods_path = format!("{}\\Mods", path);
-    DirBuilder::new()
-        .recursive(true)
-        .create(&mods_path)
-        .unwrap();
-
-    let m = match fs::read_dir("mods") {
-        Ok(entries) => entries,
-        Err(_) => {
-            rfd::MessageDialog::new()
-                .set_level(rfd::MessageLevel::Error)
-                .set_title("Error")
-                .set_description("For now only local mods are supported create a folder in steamtools named mods and drop your MelonLoader (.dll) into! Example: GameName.dll")
-                .show();
-            return None;
-        }
-    };
+    DirBuilder::new().recursive(true).create(&mods_path)?;
+
+    let m = fs::read_dir(paths::mods_dir()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "For now only local mods are supported create the folder {} and drop your MelonLoader (.dll) into! Example: GameName.dll",
+                paths::mods_dir().display()
+            ),
+        )
+    })?;
 
     for m in m {
         let entry = match m {
             Ok(e) => e,
             Err(e) => {
-                println!("ERROR: {}", e);
+                warn!("Reading mods: {e}");
                 continue;
             }
         };
@@ -123,101 +152,116 @@ pub fn install_melonloader(path: &str, melon_loader: bool) -> Option<()> {
             continue;
         }
 
-        dbg!(&PathBuf::from(path).file_name().unwrap().to_str().unwrap());
-        if pathb.file_stem().unwrap().to_str().unwrap()
-            == PathBuf::from(path).file_name().unwrap().to_str().unwrap()
+        if pathb.file_stem().and_then(|s| s.to_str())
+            == PathBuf::from(path).file_name().and_then(|s| s.to_str())
         {
-            fs::copy(
-                pathb.file_name().unwrap(),
+            atomic::copy(
+                &pathb,
                 format!("{}\\{}", &mods_path, pathb.file_name().unwrap().display()),
-            )
-            .ok()?;
+            )?;
         }
     }
 
-    Some(())
+    Ok(())
 }
 
-#[must_use]
+/// Updates `current_games` from the lua files in `config/stplug-in` and the
+/// installed app manifests, returns the new list and what changed. Fails if
+/// the main Steam library can't be read.
+#[cfg(feature = "net")]
 pub fn get_games(
     path: impl Into<PathBuf> + Copy,
     current_games: HashMap<u32, Game>,
-) -> HashMap<u32, Game> {
+) -> io::Result<(HashMap<u32, Game>, Changes)> {
     let mut p = path.into();
     p.push("config");
     p.push("stplug-in");
 
-    let mut gp = path.into();
-    gp.push("steamapps");
-
+    let previous = current_games.clone();
     let mut games: HashMap<u32, Game> = current_games;
 
     let entries = match fs::read_dir(p) {
         Ok(entries) => entries,
         Err(e) => {
-            eprintln!("Directory doesnt exist. {}", e);
-            return games;
+            warn!("Directory doesnt exist. {}", e);
+            return Ok((games, Changes::default()));
         }
     };
 
-    let mut name: HashMap<u32, String> = HashMap::new();
+    let mut game_dirs: HashMap<u32, String> = HashMap::new();
+    let mut builds: HashMap<u32, u32> = HashMap::new();
+
+    let mut installed: HashMap<u32, String> = HashMap::new();
+
+    for (i, library) in library::library_folders(path.into())
+        .into_iter()
+        .enumerate()
+    {
+        let gp = library.join("steamapps");
+        let entries = match fs::read_dir(&gp) {
+            Ok(entries) => entries,
+            // Other library folders can be on drives which aren't connected
+            Err(e) if i > 0 => {
+                warn!("Library {}: {e}", library.display());
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
 
-    let installed: HashMap<u32, String> = match fs::read_dir(&gp) {
-        Ok(entries) => entries,
-        Err(e) => {
-            rfd::MessageDialog::new()
-                .set_level(rfd::MessageLevel::Error)
-                .set_buttons(rfd::MessageButtons::Ok)
-                .set_title("Error")
-                .set_description(e.to_string())
-                .show();
-            return games;
-        }
-    }
-    .filter_map(|res| res.ok())
-    .filter(|f| f.path().is_file())
-    .filter_map(|entry| {
-        let fname = entry.file_name().into_string().ok()?;
-        if fname.starts_with("appmanifest_") && fname.ends_with(".acf") {
-            debug!("Game found: {}", &fname);
-            let id_part = &fname["appmanifest_".len()..fname.len() - ".acf".len()];
-            let id = id_part.parse::<u32>().unwrap();
-            let mut file_ptbuf = gp.to_path_buf();
-            file_ptbuf.push(&fname);
-
-            let text = fs::read_to_string(file_ptbuf).unwrap();
-
-            for line in text.lines() {
-                let line = line.trim();
-                if line.starts_with("\"name\"") {
-                    if let Some((_, value)) = line.split_once('"') {
-                        if let Some((_, value)) = value.split_once('"') {
-                            name.insert(
-                                id,
-                                format!(
-                                    "{}\\steamapps\\common\\{}",
-                                    Into::<PathBuf>::into(path).display(),
-                                    value.trim()[1..value.len() - 3].to_string()
-                                ),
-                            );
+        installed.extend(
+            entries
+                .filter_map(|res| res.ok())
+                .filter(|f| f.path().is_file())
+                .filter_map(|entry| {
+                    let fname = entry.file_name().into_string().ok()?;
+                    if fname.starts_with("appmanifest_") && fname.ends_with(".acf") {
+                        debug!("Game found: {}", &fname);
+                        let id_part = &fname["appmanifest_".len()..fname.len() - ".acf".len()];
+                        let Ok(id) = id_part.parse::<u32>() else {
+                            warn!("Skipping {fname}, no appid in the name");
+                            return None;
+                        };
+                        let mut file_ptbuf = gp.to_path_buf();
+                        file_ptbuf.push(&fname);
+
+                        let text = match fs::read_to_string(&file_ptbuf) {
+                            Ok(text) => text,
+                            Err(e) => {
+                                warn!("Skipping {}: {e}", file_ptbuf.display());
+                                return None;
+                            }
+                        };
+
+                        for line in text.lines() {
+                            let line = line.trim();
+                            if let Some(value) = line.strip_prefix("\"buildid\"")
+                                && let Ok(build) = value.trim().trim_matches('"').parse::<u32>()
+                            {
+                                builds.insert(id, build);
+                            }
+                        }
+                        match library::game_dir(&library, id) {
+                            Ok(dir) => {
+                                game_dirs.insert(id, dir.to_string_lossy().to_string());
+                            }
+                            Err(e) => warn!("Manifest of {id}: {e}"),
                         }
+                        Some((id, fname))
+                    } else {
+                        None
                     }
-                }
-            }
-            Some((id, fname))
-        } else {
-            None
-        }
-    })
-    .collect();
+                }),
+        );
+    }
 
     debug!("Installed Games: {:#?}", installed);
 
+    let icons_dir = paths::icons_dir();
     let mut icons: Option<HashMap<u32, PathBuf>> = None;
-    if Path::new("icons").exists() {
+    if icons_dir.exists() {
         icons = Some(HashMap::new());
 
-        for entry in fs::read_dir("icons").unwrap() {
+        for entry in fs::read_dir(&icons_dir).unwrap() {
             let entry = entry.unwrap();
             let path = entry.path();
 
@@ -233,6 +277,9 @@ pub fn get_games(
         }
     }
 
+    // Games whose lua file is gone get removed from the list
+    let mut listed: HashSet<u32> = HashSet::new();
+
     'entries: for entry in entries {
         let entry = match entry {
             Ok(e) => e,
@@ -248,20 +295,26 @@ pub fn get_games(
             let appid_i = match appid.to_string_lossy().parse::<u32>() {
                 Ok(i) => i,
                 Err(_) => {
-                    rfd::MessageDialog::new()
-                        .set_description(format!(
-                            "Failed to parse {} please use appid. Skipping entry",
-                            appid.to_string_lossy()
-                        ))
-                        .set_buttons(rfd::MessageButtons::Ok);
+                    warn!(
+                        "Failed to parse {} please use appid. Skipping entry",
+                        appid.to_string_lossy()
+                    );
                     continue 'entries;
                 }
             };
 
-            if let Some(ic) = icons.as_ref() {
-                if ic.contains_key(&appid_i) {
-                    continue 'entries;
+            listed.insert(appid_i);
+
+            // Known games only need their install state refreshed
+            if let Some(ic) = icons.as_ref()
+                && ic.contains_key(&appid_i)
+            {
+                if let Some(game) = games.get_mut(&appid_i) {
+                    game.installed = installed.contains_key(&appid_i);
+                    game.path = game_dirs.get(&appid_i).cloned().unwrap_or_default();
+                    game.build_id = builds.get(&appid_i).copied().unwrap_or_default();
                 }
+                continue 'entries;
             }
 
             let url = format!("{}{}", STEAM_URL, appid.display());
@@ -284,34 +337,106 @@ pub fn get_games(
                     details: if let Some(r) = resp.get(&appid.to_string_lossy().to_string())
                         && let Some(data) = &r.data
                     {
-                        if !Path::new(&format!("icons/{}.jpg", appid.display())).exists() {
+                        if !paths::icon_path(appid_i).exists() {
                             debug!("Image Asset: {} done", data.name);
-                            DirBuilder::new().recursive(true).create("icons").unwrap();
+                            DirBuilder::new()
+                                .recursive(true)
+                                .create(&icons_dir)
+                                .unwrap();
                             let bytes = blocking::get(&data.header_image)
                                 .unwrap()
                                 .bytes()
                                 .unwrap_or_default();
-                            let mut file =
-                                File::create(format!("icons/{}.jpg", appid.display())).unwrap();
-                            file.write_all(&bytes).unwrap();
+                            if let Err(e) = atomic::write(paths::icon_path(appid_i), &bytes) {
+                                warn!("Saving the icon of {appid_i}: {e}");
+                            }
                         }
                         data.clone()
                     } else {
                         AppData::default()
                     },
                     path: if installed_val {
-                        name.get(&appid_i).unwrap().to_string()
+                        game_dirs.get(&appid_i).cloned().unwrap_or_default()
                     } else {
                         String::new()
                     },
                     installed: installed_val,
-                    ..Default::default()
+                    build_id: builds.get(&appid_i).copied().unwrap_or_default(),
                 },
             );
         }
     }
 
-    games
+    games.retain(|appid, _| listed.contains(appid));
+    let changes = Changes::between(&previous, &games);
+    if !changes.is_empty() {
+        info!("Games changed: {}", changes.summary());
+    }
+    Ok((games, changes))
+}
+
+/// Size of a folder and everything in it, unreadable entries are skipped.
+pub fn dir_size(path: impl AsRef<Path>) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| match e.file_type() {
+            Ok(t) if t.is_dir() => dir_size(e.path()),
+            Ok(_) => e.metadata().map_or(0, |m| m.len()),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Formats a byte count for humans, e.g. `1.5 GB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM` (UTC).
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let (hour, minute) = ((secs % 86400) / 3600, (secs % 3600) / 60);
+
+    // Civil from days, see https://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}")
+}
+
+/// Opens a file, folder or url with the default application of the system.
+pub fn open(target: impl AsRef<std::ffi::OsStr>) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    Command::new(program).arg(target).spawn().map(|_| ())
 }
 
 impl Game {
@@ -0,0 +1,372 @@
+//! # library
+//!
+//! Steam library folders (`steamapps/libraryfolders.vdf`) and moving games
+//! between them.
+
+use std::fs::{self, File};
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{self, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+
+use crate::atomic;
+use crate::client::{WriteOp, guard};
+use crate::proton::prefix_dir;
+use crate::vdf::{self, Document};
+use crate::{Game, dir_size};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Copying,
+    Verifying,
+    Cleaning,
+}
+
+/// Progress of [`move_game`], `done` and `total` are in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub stage: Stage,
+    pub done: u64,
+    pub total: u64,
+}
+
+fn libraryfolders_path(path: &Path) -> PathBuf {
+    path.join("steamapps").join("libraryfolders.vdf")
+}
+
+/// Lists all library folders, the Steam folder itself is always first.
+pub fn library_folders(path: impl AsRef<Path>) -> Vec<PathBuf> {
+    let path = path.as_ref();
+    let mut folders = vec![path.to_path_buf()];
+
+    if let Ok(doc) = vdf::read(libraryfolders_path(path))
+        && let Some(list) = doc.get("libraryfolders")
+    {
+        for (_, folder) in list.entries() {
+            if let Some(p) = folder.get_str("path").map(PathBuf::from)
+                && !folders.iter().any(|f| same_folder(f, &p))
+            {
+                folders.push(p);
+            }
+        }
+    }
+
+    folders
+}
+
+fn same_folder(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+pub(crate) fn manifest_path(library: &Path, appid: u32) -> PathBuf {
+    library
+        .join("steamapps")
+        .join(format!("appmanifest_{appid}.acf"))
+}
+
+/// Returns the library folder a game is installed in.
+pub fn library_of(path: impl AsRef<Path>, appid: u32) -> Option<PathBuf> {
+    library_folders(path)
+        .into_iter()
+        .find(|library| manifest_path(library, appid).is_file())
+}
+
+/// Reads `installdir` from the manifest of a game.
+pub fn install_dir(library: &Path, appid: u32) -> io::Result<String> {
+    vdf::read(manifest_path(library, appid))?
+        .path(&["AppState", "installdir"])
+        .and_then(vdf::Vdf::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Manifest has no installdir"))
+}
+
+/// Install folder of a game in `library`, `steamapps/common/<installdir>`.
+pub fn game_dir(library: &Path, appid: u32) -> io::Result<PathBuf> {
+    Ok(library
+        .join("steamapps")
+        .join("common")
+        .join(install_dir(library, appid)?))
+}
+
+fn hash_file(path: &Path, mut on_read: impl FnMut(u64)) -> io::Result<u64> {
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; 1 << 20];
+    let mut hasher = DefaultHasher::new();
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buf[..n]);
+        on_read(n as u64);
+    }
+}
+
+/// Copies `from` into `to`, returning every copied file with its size and hash.
+/// Symlinks are copied as links, Proton prefixes link `z:` to `/`.
+fn copy_dir(
+    from: &Path,
+    to: &Path,
+    files: &mut Vec<(PathBuf, u64, u64)>,
+    on_copy: &mut impl FnMut(u64),
+) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target, files, on_copy)?;
+            continue;
+        }
+        #[cfg(unix)]
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+            continue;
+        }
+
+        let mut src = File::open(entry.path())?;
+        let mut dst = File::create(&target)?;
+        let mut buf = vec![0u8; 1 << 20];
+        let mut hasher = DefaultHasher::new();
+        let mut size = 0;
+        loop {
+            let n = src.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.write(&buf[..n]);
+            dst.write_all(&buf[..n])?;
+            size += n as u64;
+            on_copy(n as u64);
+        }
+        dst.sync_all()?;
+        files.push((target, size, hasher.finish()));
+    }
+    Ok(())
+}
+
+/// Moves the app entry in `libraryfolders.vdf`, without `from` it is only added.
+/// Steam rebuilds the list on its own, so this is only best effort to keep it
+/// consistent until then.
+pub(crate) fn update_libraryfolders(
+    path: &Path,
+    appid: u32,
+    from: Option<&Path>,
+    to: &Path,
+    size: u64,
+) {
+    let file = libraryfolders_path(path);
+    let Ok(mut doc) = Document::read(&file) else {
+        return;
+    };
+    let appid = appid.to_string();
+
+    let folders = doc
+        .to_vdf()
+        .get("libraryfolders")
+        .map(|l| l.entries().to_vec())
+        .unwrap_or_default();
+    doc.edit(|edit| {
+        for (key, folder) in folders {
+            let Some(folder_path) = folder.get_str("path").map(Path::new) else {
+                continue;
+            };
+            if from.is_some_and(|from| same_folder(folder_path, from)) {
+                edit.remove(&["libraryfolders", &key, "apps", &appid]);
+            } else if same_folder(folder_path, to) {
+                edit.set(&["libraryfolders", &key, "apps", &appid], &size.to_string());
+            }
+        }
+    });
+
+    if let Err(e) = doc.write(&file) {
+        warn!("Updating {}: {e}", file.display());
+    }
+}
+
+/// Moves an installed game to another library folder.
+///
+/// The game folder, its Proton prefix and its manifest are copied first and
+/// every file is checked by size and hash, the source is only removed
+/// afterwards. On success `game.path` points to the new location.
+pub fn move_game(
+    path: impl AsRef<Path>,
+    game: &mut Game,
+    target: impl AsRef<Path>,
+    progress: impl FnMut(Progress),
+) -> io::Result<()> {
+    guard(WriteOp::MoveGame)?;
+    move_files(path.as_ref(), game, target.as_ref(), progress)
+}
+
+/// [`move_game`] without checking for Steam.
+fn move_files(
+    path: &Path,
+    game: &mut Game,
+    target: &Path,
+    mut progress: impl FnMut(Progress),
+) -> io::Result<()> {
+    let source = library_of(path, game.appid)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Game is not installed"))?;
+    if same_folder(&source, target) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Game is already in this library",
+        ));
+    }
+
+    let installdir = install_dir(&source, game.appid)?;
+    let from = source.join("steamapps").join("common").join(&installdir);
+    let to = target.join("steamapps").join("common").join(&installdir);
+    if to.exists() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+
+    // The saves of Proton games are in there, left behind it would look orphaned
+    let prefix = Some(prefix_dir(&source, game.appid)).filter(|p| p.is_dir());
+    let to_prefix = prefix_dir(target, game.appid);
+    let prefix = match prefix {
+        Some(_) if to_prefix.exists() => {
+            warn!("{} already exists, keeping the prefix", to_prefix.display());
+            None
+        }
+        prefix => prefix,
+    };
+
+    let total = dir_size(&from) + prefix.as_ref().map_or(0, dir_size);
+    let mut done = 0;
+    let mut files = Vec::new();
+    info!("Moving {} to {}", from.display(), to.display());
+
+    let mut on_copy = |n| {
+        done += n;
+        progress(Progress {
+            stage: Stage::Copying,
+            done,
+            total,
+        });
+    };
+    let copied = copy_dir(&from, &to, &mut files, &mut on_copy)
+        .and_then(|_| match &prefix {
+            Some(prefix) => copy_dir(prefix, &to_prefix, &mut files, &mut on_copy),
+            None => Ok(()),
+        })
+        .and_then(|_| {
+            atomic::copy(
+                manifest_path(&source, game.appid),
+                manifest_path(target, game.appid),
+            )
+        });
+
+    let mut done = 0;
+    let verified = copied.and_then(|_| {
+        for (file, size, hash) in &files {
+            let on_disk = fs::metadata(file)?.len();
+            let check = hash_file(file, |n| {
+                done += n;
+                progress(Progress {
+                    stage: Stage::Verifying,
+                    done,
+                    total,
+                });
+            })?;
+            if on_disk != *size || check != *hash {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Verification failed for {}", file.display()),
+                ));
+            }
+        }
+        Ok(())
+    });
+
+    if let Err(e) = verified {
+        // Leave the source untouched and throw away the partial copy
+        fs::remove_dir_all(&to).ok();
+        if prefix.is_some() {
+            fs::remove_dir_all(&to_prefix).ok();
+        }
+        fs::remove_file(manifest_path(target, game.appid)).ok();
+        return Err(e);
+    }
+
+    progress(Progress {
+        stage: Stage::Cleaning,
+        done: total,
+        total,
+    });
+    fs::remove_dir_all(&from)?;
+    if let Some(prefix) = &prefix {
+        fs::remove_dir_all(prefix)?;
+    }
+    fs::remove_file(manifest_path(&source, game.appid))?;
+    update_libraryfolders(path, game.appid, Some(&source), target, total);
+
+    game.path = to.to_string_lossy().to_string();
+    game.installed = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proton;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn moves_prefix() {
+        let root = TempDir::new("library");
+        let (steam, other) = (root.join("steam"), root.join("other"));
+        fs::create_dir_all(steam.join("steamapps").join("common").join("Game")).unwrap();
+        fs::create_dir_all(other.join("steamapps")).unwrap();
+        fs::write(
+            libraryfolders_path(&steam),
+            format!(
+                "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+                steam.display(),
+                other.display()
+            ),
+        )
+        .unwrap();
+        fs::write(
+            manifest_path(&steam, 10),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"10\"\n\t\"installdir\"\t\t\"Game\"\n}\n",
+        )
+        .unwrap();
+        fs::write(steam.join("steamapps/common/Game/game.exe"), "game").unwrap();
+        let saves = prefix_dir(&steam, 10).join("pfx").join("drive_c");
+        fs::create_dir_all(&saves).unwrap();
+        fs::write(saves.join("save.dat"), "save").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("drive_c", saves.with_file_name("c:")).unwrap();
+
+        let mut game = Game {
+            appid: 10,
+            ..Default::default()
+        };
+        move_files(&steam, &mut game, &other, |_| {}).unwrap();
+
+        let moved = prefix_dir(&other, 10).join("pfx");
+        assert_eq!(
+            fs::read_to_string(moved.join("drive_c").join("save.dat")).unwrap(),
+            "save"
+        );
+        #[cfg(unix)]
+        assert!(fs::symlink_metadata(moved.join("c:")).unwrap().is_symlink());
+        assert!(!prefix_dir(&steam, 10).exists());
+        assert_eq!(Path::new(&game.path), other.join("steamapps/common/Game"));
+
+        // Found in the other library and not orphaned
+        let prefixes = proton::get_prefixes(&steam);
+        assert_eq!(prefixes.len(), 1);
+        assert!(!prefixes[0].orphaned);
+    }
+}
@@ -0,0 +1,75 @@
+//! # localconfig
+//!
+//! Per account game settings in `userdata/<accountid>/config/localconfig.vdf`,
+//! for now the launch options.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::info;
+
+use crate::atomic;
+use crate::client::{WriteOp, guard};
+use crate::userdata::user_dir;
+use crate::vdf::Document;
+
+/// Location of the per game settings inside `localconfig.vdf`.
+const APPS_PATH: [&str; 5] = ["UserLocalConfigStore", "Software", "Valve", "Steam", "apps"];
+
+pub fn localconfig_path(path: impl AsRef<Path>, account: u32) -> PathBuf {
+    user_dir(path, account)
+        .join("config")
+        .join("localconfig.vdf")
+}
+
+fn options_path(appid: &str) -> [&str; 7] {
+    let [a, b, c, d, e] = APPS_PATH;
+    [a, b, c, d, e, appid, "LaunchOptions"]
+}
+
+/// Returns the launch options of a game, empty if none are set.
+pub fn get_launch_options(path: impl AsRef<Path>, account: u32, appid: u32) -> String {
+    Document::read(localconfig_path(path, account))
+        .ok()
+        .and_then(|doc| doc.get(&options_path(&appid.to_string())))
+        .unwrap_or_default()
+}
+
+/// Sets the launch options of several games at once, an empty string removes
+/// them. The previous file is kept at [`atomic::backup_path`], which is
+/// returned.
+///
+/// Steam writes `localconfig.vdf` when it exits, so this refuses to write while
+/// it is running.
+pub fn set_launch_options(
+    path: impl AsRef<Path>,
+    account: u32,
+    options: &[(u32, &str)],
+) -> io::Result<PathBuf> {
+    guard(WriteOp::LaunchOptions)?;
+
+    let file = localconfig_path(path, account);
+    let mut doc = Document::read(&file)?;
+
+    doc.edit(|edit| {
+        for (appid, value) in options {
+            let appid = appid.to_string();
+            if value.is_empty() {
+                edit.remove(&options_path(&appid));
+            } else {
+                edit.set(&options_path(&appid), value);
+            }
+        }
+    });
+
+    atomic::Writer::new(&file)
+        .backup(true)
+        .write(doc.as_str())?;
+    let backup = atomic::backup_path(&file);
+    info!(
+        "Backup of {} written to {}",
+        file.display(),
+        backup.display()
+    );
+    Ok(backup)
+}
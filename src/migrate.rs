@@ -0,0 +1,294 @@
+//! # migrate
+//!
+//! Upgrades stored data after Steamtools was updated. Every [`Migration`] is
+//! keyed by the version that introduced it and runs once, oldest first, when
+//! coming from an older version.
+//!
+//! Stored values are handled as a [`State`] of key to JSON text so the GUI's
+//! storage and headless tools can share the steps.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+
+use crate::bserializer::GameMap;
+use crate::db::LibraryDb;
+use crate::paths;
+
+/// Stored values by key.
+pub type State = BTreeMap<String, String>;
+
+pub struct Migration {
+    /// First version with the new layout.
+    pub version: &'static str,
+    pub description: &'static str,
+    pub run: fn(&mut State, &mut Report) -> io::Result<()>,
+}
+
+/// What [`migrate`] did, meant to be shown to the user.
+#[derive(Debug, Default, Clone)]
+pub struct Report {
+    pub from: String,
+    pub to: String,
+    /// Descriptions of the migrations that ran.
+    pub applied: Vec<String>,
+    pub notes: Vec<String>,
+    /// Data that could not be upgraded and was dropped.
+    pub cleared: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl Report {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty()
+            && self.notes.is_empty()
+            && self.cleared.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Updated from {} to {}", self.from, self.to)?;
+        for (title, lines) in [
+            ("Migrated", &self.applied),
+            ("Notes", &self.notes),
+            ("Cleared", &self.cleared),
+            ("Failed", &self.failed),
+        ] {
+            if lines.is_empty() {
+                continue;
+            }
+            writeln!(f, "\n{title}:")?;
+            for line in lines {
+                writeln!(f, "  {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses `major.minor.patch`, missing parts count as 0.
+pub fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = version.trim().split('.').map(str::parse::<u32>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// Runs the migrations newer than `from` up to `to`. An empty `from` is a
+/// fresh install and needs nothing.
+pub fn migrate(state: &mut State, from: &str, to: &str, migrations: &[Migration]) -> Report {
+    let mut report = Report {
+        from: from.to_string(),
+        to: to.to_string(),
+        ..Default::default()
+    };
+    if from.is_empty() || from == to {
+        return report;
+    }
+
+    let Some(to_version) = parse_version(to) else {
+        report.failed.push(format!("Unknown version {to}"));
+        return report;
+    };
+    let from_version = parse_version(from).unwrap_or_else(|| {
+        report
+            .notes
+            .push(format!("Unknown version {from}, running every migration"));
+        (0, 0, 0)
+    });
+    if from_version > to_version {
+        report.notes.push(format!(
+            "The data was written by the newer version {from} and was left as is"
+        ));
+        return report;
+    }
+
+    for migration in migrations {
+        let Some(version) = parse_version(migration.version) else {
+            continue;
+        };
+        if version <= from_version || version > to_version {
+            continue;
+        }
+
+        info!(
+            "Migrating to {}: {}",
+            migration.version, migration.description
+        );
+        match (migration.run)(state, &mut report) {
+            Ok(()) => report
+                .applied
+                .push(format!("{}: {}", migration.version, migration.description)),
+            Err(e) => {
+                warn!("Migration {} failed: {e}", migration.version);
+                report.failed.push(format!(
+                    "{}: {} ({e})",
+                    migration.version, migration.description
+                ));
+            }
+        }
+    }
+    report
+}
+
+/// Older versions kept their data next to the binary or in whatever folder
+/// they were started from.
+fn legacy_dirs() -> io::Result<Vec<PathBuf>> {
+    let mut dirs = vec![env::current_dir()?];
+    if let Some(dir) = env::current_exe()?.parent()
+        && !dirs.contains(&dir.to_path_buf())
+    {
+        dirs.push(dir.to_path_buf());
+    }
+    Ok(dirs)
+}
+
+/// The game list of 0.6.5 and older, [`move_legacy_data`] moves it into the
+/// data folder unless one is already there.
+fn steam_bins() -> io::Result<Vec<PathBuf>> {
+    let mut bins = vec![paths::legacy_games_path()];
+    bins.extend(legacy_dirs()?.into_iter().map(|dir| dir.join("steam.bin")));
+    bins.retain(|path| path.is_file());
+    Ok(bins)
+}
+
+fn move_legacy_data(_state: &mut State, report: &mut Report) -> io::Result<()> {
+    for dir in legacy_dirs()? {
+        for moved in paths::migrate_legacy(&dir)? {
+            report.notes.push(format!("Moved {moved}"));
+        }
+    }
+    Ok(())
+}
+
+/// Moves the games of `bin` into the database at `db_path` unless it has
+/// games already. `bin` is only removed once nothing in it can get lost.
+fn import_steam_bin(bin: &Path, db_path: &Path, report: &mut Report) -> io::Result<()> {
+    let games = match File::open(bin).and_then(|mut file| GameMap::read_from(&mut file)) {
+        Ok(games) => games,
+        Err(e) => {
+            report.notes.push(format!(
+                "{} could not be read and was kept, the game list is fetched again ({e})",
+                bin.display()
+            ));
+            return Ok(());
+        }
+    };
+
+    let mut db = LibraryDb::open(db_path)?;
+    if db.is_empty() {
+        let count = games.len();
+        db.transaction(|tx| {
+            tx.replace(games);
+            Ok(())
+        })?;
+        report.notes.push(format!(
+            "Moved {count} games from {} to {}",
+            bin.display(),
+            db_path.display()
+        ));
+    }
+
+    match fs::remove_file(bin) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn games_to_db(_state: &mut State, report: &mut Report) -> io::Result<()> {
+    let db_path = paths::library_path();
+    for bin in steam_bins()? {
+        import_steam_bin(&bin, &db_path, report)?;
+    }
+    Ok(())
+}
+
+/// Migrations of the data shared by every frontend, oldest first.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "0.6.6",
+        description: "Move data from the working directory into the platform folders",
+        run: move_legacy_data,
+    },
+    Migration {
+        version: "0.6.6",
+        description: "Move the game list from steam.bin into the library database",
+        run: games_to_db,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn rename(state: &mut State, _report: &mut Report) -> io::Result<()> {
+        if let Some(value) = state.remove("old") {
+            state.insert("new".to_string(), value);
+        }
+        Ok(())
+    }
+
+    fn fail(_state: &mut State, _report: &mut Report) -> io::Result<()> {
+        Err(io::Error::other("broken"))
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: "0.5.0",
+            description: "too old",
+            run: fail,
+        },
+        Migration {
+            version: "0.6.0",
+            description: "rename",
+            run: rename,
+        },
+        Migration {
+            version: "0.7.0",
+            description: "too new",
+            run: fail,
+        },
+    ];
+
+    #[test]
+    fn runs_migrations_in_range() {
+        let mut state = State::from([("old".to_string(), "1".to_string())]);
+        let report = migrate(&mut state, "0.5.1", "0.6.5", TEST_MIGRATIONS);
+        assert_eq!(report.applied, ["0.6.0: rename"]);
+        assert!(report.failed.is_empty());
+        assert_eq!(state.get("new").map(String::as_str), Some("1"));
+
+        // Same version, fresh install and downgrades change nothing
+        assert!(migrate(&mut state, "0.6.5", "0.6.5", TEST_MIGRATIONS).is_empty());
+        assert!(migrate(&mut state, "", "0.6.5", TEST_MIGRATIONS).is_empty());
+        let newer = migrate(&mut state, "0.8.0", "0.6.5", TEST_MIGRATIONS);
+        assert!(newer.applied.is_empty() && !newer.notes.is_empty());
+    }
+
+    #[test]
+    fn keeps_steam_bin_on_failure() {
+        let dir = TempDir::new("migrate");
+        let bin = dir.join("steam.bin");
+        fs::write(&bin, 0u32.to_le_bytes()).unwrap();
+        // Written by a future version, can't be opened
+        let db = dir.join("library.db");
+        fs::write(&db, u32::MAX.to_le_bytes()).unwrap();
+
+        let mut report = Report::default();
+        assert!(import_steam_bin(&bin, &db, &mut report).is_err());
+        assert!(bin.exists());
+
+        fs::remove_file(&db).unwrap();
+        import_steam_bin(&bin, &db, &mut report).unwrap();
+        assert!(!bin.exists());
+    }
+}
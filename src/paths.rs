@@ -0,0 +1,410 @@
+//! # paths
+//!
+//! Where Steamtools keeps its files, resolved once per platform:
+//!
+//! | | Linux (XDG) | Windows | macOS |
+//! |---|---|---|---|
+//! | config | `$XDG_CONFIG_HOME/steamtools` | `%APPDATA%\Steamtools` | `~/Library/Application Support/Steamtools` |
+//! | data | `$XDG_DATA_HOME/steamtools` | `%APPDATA%\Steamtools` | `~/Library/Application Support/Steamtools` |
+//! | cache | `$XDG_CACHE_HOME/steamtools` | `%LOCALAPPDATA%\Steamtools\cache` | `~/Library/Caches/Steamtools` |
+//! | logs | `$XDG_STATE_HOME/steamtools` | `%LOCALAPPDATA%\Steamtools\logs` | `~/Library/Logs/Steamtools` |
+//!
+//! In portable mode, switched on by [`PORTABLE_MARKER`] next to the binary or
+//! by [`set_portable`], all of them are folders next to the executable
+//! instead and a Steam install inside of that folder is stored relative to
+//! it, see [`store_path`].
+//!
+//! Older versions wrote everything into the working directory, see
+//! [`migrate_legacy`].
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{info, warn};
+
+use crate::atomic;
+
+/// File next to the executable that turns on portable mode.
+pub const PORTABLE_MARKER: &str = "portable.txt";
+
+static PORTABLE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone)]
+pub struct Dirs {
+    pub config: PathBuf,
+    pub data: PathBuf,
+    pub cache: PathBuf,
+    pub logs: PathBuf,
+    /// Folder of the executable when running portable.
+    pub portable: Option<PathBuf>,
+}
+
+fn var(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+impl Dirs {
+    #[cfg(target_os = "windows")]
+    fn platform() -> Option<Self> {
+        let roaming = var("APPDATA")?.join("Steamtools");
+        let local = var("LOCALAPPDATA")?.join("Steamtools");
+        Some(Self {
+            config: roaming.clone(),
+            data: roaming,
+            cache: local.join("cache"),
+            logs: local.join("logs"),
+            portable: None,
+        })
+    }
+
+    #[cfg(target_os = "macos")]
+    fn platform() -> Option<Self> {
+        let library = var("HOME")?.join("Library");
+        let support = library.join("Application Support").join("Steamtools");
+        Some(Self {
+            config: support.clone(),
+            data: support,
+            cache: library.join("Caches").join("Steamtools"),
+            logs: library.join("Logs").join("Steamtools"),
+            portable: None,
+        })
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn platform() -> Option<Self> {
+        let home = var("HOME");
+        let xdg = |name: &str, fallback: &str| {
+            var(name)
+                .filter(|p| p.is_absolute())
+                .or_else(|| home.as_ref().map(|h| h.join(fallback)))
+                .map(|p| p.join("steamtools"))
+        };
+        Some(Self {
+            config: xdg("XDG_CONFIG_HOME", ".config")?,
+            data: xdg("XDG_DATA_HOME", ".local/share")?,
+            cache: xdg("XDG_CACHE_HOME", ".cache")?,
+            logs: xdg("XDG_STATE_HOME", ".local/state")?,
+            portable: None,
+        })
+    }
+
+    /// Everything in one folder, used when the platform folders are unknown.
+    fn in_dir(dir: PathBuf) -> Self {
+        Self {
+            config: dir.clone(),
+            data: dir.clone(),
+            cache: dir.clone(),
+            logs: dir,
+            portable: None,
+        }
+    }
+
+    fn portable(root: PathBuf) -> Self {
+        Self {
+            config: root.join("config"),
+            data: root.join("data"),
+            cache: root.join("cache"),
+            logs: root.join("logs"),
+            portable: Some(root),
+        }
+    }
+
+    fn resolve() -> Self {
+        if let Some(root) = exe_dir()
+            && (PORTABLE.load(Ordering::Relaxed) || root.join(PORTABLE_MARKER).exists())
+        {
+            return Self::portable(root);
+        }
+
+        // No home folder
+        Self::platform().unwrap_or_else(|| Self::in_dir(PathBuf::from(".")))
+    }
+}
+
+static DIRS: OnceLock<Dirs> = OnceLock::new();
+
+fn exe_dir() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    exe.parent().map(Path::to_path_buf)
+}
+
+/// Turns on portable mode for this run, e.g. for `--portable`. Has no effect
+/// once a path was used.
+pub fn set_portable() {
+    if DIRS.get().is_some_and(|d| d.portable.is_none()) {
+        warn!("Portable mode was requested after the folders were resolved");
+    }
+    PORTABLE.store(true, Ordering::Relaxed);
+}
+
+pub fn is_portable() -> bool {
+    dirs().portable.is_some()
+}
+
+/// The folders of this run, created on first use.
+pub fn dirs() -> &'static Dirs {
+    DIRS.get_or_init(|| {
+        let dirs = Dirs::resolve();
+        for dir in [&dirs.config, &dirs.data, &dirs.cache, &dirs.logs] {
+            if let Err(e) = fs::create_dir_all(dir) {
+                warn!("Creating {}: {e}", dir.display());
+            }
+        }
+        dirs
+    })
+}
+
+/// Logs where the files of this run are. The folders are usually resolved
+/// before the logger exists, the log file is in there too.
+pub fn log_dirs() {
+    let dirs = dirs();
+    match &dirs.portable {
+        Some(root) => info!("Portable mode in {}", root.display()),
+        None if dirs.data == Path::new(".") => {
+            warn!("No home folder, keeping files in the working directory")
+        }
+        None => info!(
+            "Config in {}, data in {}",
+            dirs.config.display(),
+            dirs.data.display()
+        ),
+    }
+}
+
+pub fn config_dir() -> &'static Path {
+    &dirs().config
+}
+
+pub fn data_dir() -> &'static Path {
+    &dirs().data
+}
+
+pub fn cache_dir() -> &'static Path {
+    &dirs().cache
+}
+
+pub fn log_dir() -> &'static Path {
+    &dirs().logs
+}
+
+/// Header images of the games, `<appid>.jpg`.
+pub fn icons_dir() -> PathBuf {
+    cache_dir().join("icons")
+}
+
+pub fn icon_path(appid: u32) -> PathBuf {
+    icons_dir().join(format!("{appid}.jpg"))
+}
+
+pub fn thumbnails_dir() -> PathBuf {
+    cache_dir().join("thumbnails")
+}
+
+pub fn plugins_dir() -> PathBuf {
+    data_dir().join("plugins")
+}
+
+/// Local MelonLoader mods, `<game folder name>.dll`.
+pub fn mods_dir() -> PathBuf {
+    data_dir().join("mods")
+}
+
+pub fn melonloader_dir() -> PathBuf {
+    data_dir().join("MelonLoader")
+}
+
+pub fn backups_dir() -> PathBuf {
+    data_dir().join("backups")
+}
+
+pub fn library_path() -> PathBuf {
+    data_dir().join("library.db")
+}
+
+/// The game list of 0.6.5 and older, replaced by [`library_path`].
+pub fn legacy_games_path() -> PathBuf {
+    data_dir().join("steam.bin")
+}
+
+pub fn sessions_path() -> PathBuf {
+    data_dir().join("sessions.json")
+}
+
+pub fn hooks_path() -> PathBuf {
+    config_dir().join("hooks.json")
+}
+
+/// Launch logs of the games, `<appid>.log`.
+pub fn game_logs_dir() -> PathBuf {
+    log_dir().join("games")
+}
+
+pub fn app_log_path() -> PathBuf {
+    log_dir().join("steamtools.log")
+}
+
+/// `path` relative to `base`, None if they don't share a root (e.g. other
+/// drives on Windows). Always starts with `.` or `..`, which tells it apart
+/// from paths like `~/.steam` in [`load_path`].
+fn relative(path: &Path, base: &Path) -> Option<PathBuf> {
+    let mut path = path.components().peekable();
+    let mut base = base.components().peekable();
+    while let (Some(a), Some(b)) = (path.peek(), base.peek()) {
+        if a != b {
+            break;
+        }
+        path.next();
+        base.next();
+    }
+    if path
+        .peek()
+        .is_some_and(|c| matches!(c, Component::Prefix(_) | Component::RootDir))
+        || base
+            .peek()
+            .is_some_and(|c| matches!(c, Component::Prefix(_) | Component::RootDir))
+    {
+        // The root or drive differs
+        return None;
+    }
+
+    let mut relative: PathBuf = base.map(|_| Component::ParentDir).collect();
+    if relative.as_os_str().is_empty() {
+        relative.push(Component::CurDir);
+    }
+    relative.extend(path);
+    Some(relative)
+}
+
+/// `path` as saved with the portable folder in `root`. Only paths inside of
+/// `root` move along with it and are made relative, system folders like
+/// `~/.steam` stay absolute.
+fn portable_path(path: &Path, root: &Path) -> PathBuf {
+    if !path.is_absolute() || !path.starts_with(root) {
+        return path.to_path_buf();
+    }
+    relative(path, root).unwrap_or(path.to_path_buf())
+}
+
+/// How a path outside of Steamtools is saved: relative to the executable in
+/// portable mode where possible, so the folder can move between machines.
+pub fn store_path(path: &Path) -> PathBuf {
+    match &dirs().portable {
+        Some(root) => portable_path(path, root),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Reverses [`store_path`].
+pub fn load_path(path: &Path) -> PathBuf {
+    match &dirs().portable {
+        Some(root)
+            if path
+                .components()
+                .next()
+                .is_some_and(|c| matches!(c, Component::CurDir | Component::ParentDir)) =>
+        {
+            root.join(path)
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Files and folders 0.6.5 and older created in the working directory, with
+/// where they belong now.
+fn legacy() -> Vec<(&'static str, PathBuf)> {
+    vec![
+        ("icons", icons_dir()),
+        ("plugins", plugins_dir()),
+        ("mods", mods_dir()),
+        ("MelonLoader", melonloader_dir()),
+        ("steam.bin", legacy_games_path()),
+        // The new log is already open by the time this runs
+        ("steamtools.log", log_dir().join("steamtools-0.6.5.log")),
+    ]
+}
+
+/// Moves a file or folder, copying when it is on another drive.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            move_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::remove_dir(from)
+    } else {
+        atomic::copy(from, to)?;
+        fs::remove_file(from)
+    }
+}
+
+/// Moves data left in `dir` by older versions into the platform folders.
+/// Nothing is overwritten, returns a line per moved item.
+pub fn migrate_legacy(dir: &Path) -> io::Result<Vec<String>> {
+    let mut moved = Vec::new();
+    for (name, target) in legacy() {
+        let source = dir.join(name);
+        if !source.exists() || target.exists() {
+            continue;
+        }
+        move_path(&source, &target)?;
+        info!("Moved {} to {}", source.display(), target.display());
+        moved.push(format!("{} -> {}", source.display(), target.display()));
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn relative_paths() {
+        let base = Path::new("/media/usb/steamtools");
+        assert_eq!(
+            relative(Path::new("/media/usb/Steam"), base),
+            Some(PathBuf::from("../Steam"))
+        );
+        assert_eq!(
+            relative(Path::new("/media/usb/steamtools/Steam"), base),
+            Some(PathBuf::from("./Steam"))
+        );
+        assert_eq!(
+            relative(Path::new("/home/user/Steam"), Path::new("/")).as_deref(),
+            Some(Path::new("./home/user/Steam"))
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn portable_paths() {
+        let root = Path::new("/media/usb/steamtools");
+        assert_eq!(
+            portable_path(Path::new("/media/usb/steamtools/Steam"), root),
+            PathBuf::from("./Steam")
+        );
+        assert_eq!(
+            portable_path(Path::new("/home/user/.steam/steam"), root),
+            PathBuf::from("/home/user/.steam/steam")
+        );
+        assert_eq!(
+            portable_path(Path::new("/media/usb/Steam"), root),
+            PathBuf::from("/media/usb/Steam")
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! # proton
+//!
+//! Proton prefixes (`steamapps/compatdata/<appid>`) and compatibility tools
+//! used by Steam on Linux. Prefixes live in the library folder of their game.
+
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::client::{WriteOp, guard};
+use crate::dir_size;
+use crate::library::{game_dir, library_folders, manifest_path};
+use crate::sessions;
+use crate::vdf::{self, Document, Vdf};
+
+/// Location of `CompatToolMapping` inside `config/config.vdf`.
+const MAPPING_PATH: [&str; 5] = [
+    "InstallConfigStore",
+    "Software",
+    "Valve",
+    "Steam",
+    "CompatToolMapping",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Prefix {
+    pub appid: u32,
+    pub path: PathBuf,
+    /// Size in bytes of everything in the prefix.
+    pub size: u64,
+    /// True if the game the prefix belongs to is no longer installed.
+    pub orphaned: bool,
+}
+
+fn is_installed(libraries: &[PathBuf], appid: u32) -> bool {
+    libraries
+        .iter()
+        .any(|library| manifest_path(library, appid).is_file())
+}
+
+fn measure(libraries: &[PathBuf], appid: u32, dir: PathBuf) -> Prefix {
+    Prefix {
+        appid,
+        size: dir_size(&dir),
+        orphaned: !is_installed(libraries, appid),
+        path: dir,
+    }
+}
+
+/// Path of the prefix of a game in one library folder, it may not exist.
+pub fn prefix_dir(path: impl AsRef<Path>, appid: u32) -> PathBuf {
+    path.as_ref()
+        .join("steamapps")
+        .join("compatdata")
+        .join(appid.to_string())
+}
+
+/// Returns the prefix of a game if it has one in any library folder.
+pub fn get_prefix(path: impl AsRef<Path>, appid: u32) -> Option<Prefix> {
+    let libraries = library_folders(path);
+    let dir = libraries
+        .iter()
+        .map(|library| prefix_dir(library, appid))
+        .find(|dir| dir.is_dir())?;
+    Some(measure(&libraries, appid, dir))
+}
+
+/// Lists the prefixes of every library folder, sorted by appid. This walks all
+/// prefixes to measure them so it should not run on the ui thread.
+pub fn get_prefixes(path: impl AsRef<Path>) -> Vec<Prefix> {
+    let libraries = library_folders(path);
+    let mut prefixes = Vec::new();
+
+    for library in &libraries {
+        let Ok(entries) = fs::read_dir(library.join("steamapps").join("compatdata")) else {
+            continue;
+        };
+        prefixes.extend(
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().is_dir())
+                .filter_map(|e| Some((e.file_name().to_str()?.parse::<u32>().ok()?, e.path())))
+                .map(|(appid, dir)| measure(&libraries, appid, dir)),
+        );
+    }
+    prefixes.sort_by(|a, b| a.appid.cmp(&b.appid).then_with(|| a.path.cmp(&b.path)));
+    prefixes
+}
+
+/// Fails with [`ErrorKind::ResourceBusy`] while the game or anything else
+/// runs in the prefix, Wine keeps its files open.
+fn guard_prefix(prefix: &Prefix) -> io::Result<()> {
+    guard(WriteOp::Prefix)?;
+
+    let mut targets = vec![(prefix.appid, prefix.path.clone())];
+    // <library>/steamapps/compatdata/<appid>
+    if let Some(library) = prefix.path.ancestors().nth(3)
+        && let Ok(dir) = game_dir(library, prefix.appid)
+    {
+        targets.push((prefix.appid, dir));
+    }
+    if !sessions::running(targets).is_empty() {
+        return Err(Error::new(
+            ErrorKind::ResourceBusy,
+            "The game is running, close it before changing its prefix",
+        ));
+    }
+    Ok(())
+}
+
+/// Empties a prefix, Proton creates a fresh one on the next launch. This can
+/// take a while for large prefixes.
+pub fn reset_prefix(prefix: &Prefix) -> io::Result<()> {
+    guard_prefix(prefix)?;
+    fs::remove_dir_all(&prefix.path)?;
+    fs::create_dir(&prefix.path)
+}
+
+/// Deletes a prefix completely, see [`reset_prefix`].
+pub fn delete_prefix(prefix: &Prefix) -> io::Result<()> {
+    guard_prefix(prefix)?;
+    fs::remove_dir_all(&prefix.path)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompatTool {
+    /// Internal name used by `CompatToolMapping`, e.g. `proton_9` or `GE-Proton9-20`.
+    pub name: String,
+    pub display_name: String,
+    pub path: PathBuf,
+}
+
+/// Steam doesn't store the internal names of its own Proton builds on disk, they
+/// follow a fixed pattern though: `Proton 9.0` => `proton_9`,
+/// `Proton 5.13` => `proton_513`, `Proton - Experimental` => `proton_experimental`.
+/// Branches are the same tool, `Proton 9.0 (Beta)` is `proton_9` as well.
+fn proton_internal_name(name: &str) -> String {
+    let name = match name.find(" (") {
+        Some(branch) if name.ends_with(')') => &name[..branch],
+        _ => name,
+    };
+    let rest = name
+        .trim_start_matches("Proton")
+        .trim_start_matches([' ', '-'])
+        .trim();
+    let version = rest.strip_suffix(".0").unwrap_or(rest);
+
+    if version.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        format!("proton_{}", version.replace('.', ""))
+    } else {
+        format!("proton_{}", version.to_lowercase().replace(' ', "_"))
+    }
+}
+
+/// True if `dir` holds a Proton build. Steam installs its runtimes (e.g.
+/// `Proton EasyAntiCheat Runtime`) the same way, those have no
+/// `toolmanifest.vdf` or start something else.
+fn is_proton(dir: &Path) -> bool {
+    let Ok(doc) = vdf::read(dir.join("toolmanifest.vdf")) else {
+        return false;
+    };
+    doc.path(&["manifest", "commandline"])
+        .and_then(Vdf::as_str)
+        .and_then(|line| line.split_whitespace().next())
+        .is_some_and(|command| command.trim_start_matches('/') == "proton")
+}
+
+/// Lists the custom tools in `compatibilitytools.d` and the Proton versions
+/// installed through Steam in any library folder.
+pub fn get_compat_tools(path: impl AsRef<Path>) -> Vec<CompatTool> {
+    let path = path.as_ref();
+    let mut tools = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(path.join("compatibilitytools.d")) {
+        for dir in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let Ok(doc) = vdf::read(dir.join("compatibilitytool.vdf")) else {
+                continue;
+            };
+            let Some(list) = doc.path(&["compatibilitytools", "compat_tools"]) else {
+                continue;
+            };
+
+            for (name, tool) in list.entries() {
+                tools.push(CompatTool {
+                    name: name.clone(),
+                    display_name: tool.get_str("display_name").unwrap_or(name).to_string(),
+                    path: dir.join(tool.get_str("install_path").unwrap_or(".")),
+                });
+            }
+        }
+    }
+
+    for library in library_folders(path) {
+        let steamapps = library.join("steamapps");
+        let Ok(entries) = fs::read_dir(&steamapps) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let fname = entry.file_name().to_string_lossy().to_string();
+            if !(fname.starts_with("appmanifest_") && fname.ends_with(".acf")) {
+                continue;
+            }
+
+            let Ok(doc) = vdf::read(entry.path()) else {
+                continue;
+            };
+            let Some(app) = doc.get("AppState") else {
+                continue;
+            };
+            let (Some(name), Some(installdir)) = (app.get_str("name"), app.get_str("installdir"))
+            else {
+                continue;
+            };
+
+            let dir = steamapps.join("common").join(installdir);
+            if name.starts_with("Proton") && is_proton(&dir) {
+                tools.push(CompatTool {
+                    name: proton_internal_name(name),
+                    display_name: name.to_string(),
+                    path: dir,
+                });
+            }
+        }
+    }
+
+    tools.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+    tools
+}
+
+fn config_path(path: &Path) -> PathBuf {
+    path.join("config").join("config.vdf")
+}
+
+/// Returns the tool forced for a game in the Steam properties, if any.
+/// The appid `0` holds the default for all games.
+pub fn get_compat_tool(path: impl AsRef<Path>, appid: u32) -> Option<String> {
+    let doc = vdf::read(config_path(path.as_ref())).ok()?;
+    doc.path(&MAPPING_PATH)?
+        .get(&appid.to_string())?
+        .get_str("name")
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+/// Forces a tool for a game, `None` goes back to the default.
+///
+/// Steam overwrites `config.vdf` when it exits, so this refuses to write while
+/// it is running.
+pub fn set_compat_tool(path: impl AsRef<Path>, appid: u32, tool: Option<&str>) -> io::Result<()> {
+    guard(WriteOp::CompatTool)?;
+
+    let file = config_path(path.as_ref());
+    let mut doc = Document::read(&file)?;
+    let appid = appid.to_string();
+    let [a, b, c, d, e] = MAPPING_PATH;
+
+    doc.edit(|edit| match tool {
+        Some(name) => {
+            edit.set(&[a, b, c, d, e, &appid, "name"], name);
+            edit.set(&[a, b, c, d, e, &appid, "config"], "");
+            edit.set(&[a, b, c, d, e, &appid, "priority"], "250");
+        }
+        None => {
+            edit.remove(&[a, b, c, d, e, &appid]);
+        }
+    });
+
+    doc.write(&file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_names() {
+        for (name, internal) in [
+            ("Proton 9.0", "proton_9"),
+            ("Proton 5.13", "proton_513"),
+            ("Proton 9.0 (Beta)", "proton_9"),
+            ("Proton - Experimental", "proton_experimental"),
+            ("Proton Hotfix", "proton_hotfix"),
+        ] {
+            assert_eq!(proton_internal_name(name), internal);
+        }
+    }
+}
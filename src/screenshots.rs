@@ -0,0 +1,128 @@
+//! # screenshots
+//!
+//! Indexes the screenshots Steam keeps in
+//! `userdata/<accountid>/760/remote/<appid>/screenshots` together with the
+//! metadata from `userdata/<accountid>/760/screenshots.vdf`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::atomic;
+use crate::client::{WriteOp, guard};
+use crate::userdata::{user_dir, users};
+use crate::vdf::{self, Vdf};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Screenshot {
+    pub account: u32,
+    pub path: PathBuf,
+    /// Thumbnail generated by Steam, if there is one.
+    pub thumbnail: Option<PathBuf>,
+    pub caption: String,
+    /// Unix timestamp, taken from the metadata or the file itself.
+    pub created: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Metadata of `screenshots.vdf` for one game, keyed by file name.
+fn read_metadata(dir: &Path, appid: u32) -> HashMap<String, Vdf> {
+    let Ok(doc) = vdf::read(dir.join("screenshots.vdf")) else {
+        return HashMap::new();
+    };
+
+    doc.path(&["Screenshots", &appid.to_string()])
+        .map(|app| {
+            app.entries()
+                .iter()
+                .filter_map(|(_, shot)| {
+                    let name = Path::new(shot.get_str("filename")?).file_name()?;
+                    Some((name.to_string_lossy().to_string(), shot.clone()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the screenshots of a game for every account, newest first.
+pub fn get_screenshots(path: impl AsRef<Path>, appid: u32) -> Vec<Screenshot> {
+    let path = path.as_ref();
+    let mut shots = Vec::new();
+
+    for account in users(path) {
+        let base = user_dir(path, account).join("760");
+        let dir = base
+            .join("remote")
+            .join(appid.to_string())
+            .join("screenshots");
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        let metadata = read_metadata(&base, appid);
+        debug!(
+            "Screenshots: {} metadata entries for {}",
+            metadata.len(),
+            appid
+        );
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let file = entry.path();
+            if !file.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let meta = metadata.get(&name);
+            let num = |key: &str| meta.and_then(|m| m.get_str(key)?.parse::<u64>().ok());
+
+            let thumbnail = dir.join("thumbnails").join(&name);
+            shots.push(Screenshot {
+                account,
+                thumbnail: thumbnail.is_file().then_some(thumbnail),
+                caption: meta
+                    .and_then(|m| m.get_str("caption"))
+                    .unwrap_or_default()
+                    .to_string(),
+                created: num("creation").unwrap_or_else(|| {
+                    entry
+                        .metadata()
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                        .map_or(0, |d| d.as_secs())
+                }),
+                width: num("width").unwrap_or_default() as u32,
+                height: num("height").unwrap_or_default() as u32,
+                path: file,
+            });
+        }
+    }
+
+    shots.sort_by_key(|s| std::cmp::Reverse(s.created));
+    shots
+}
+
+/// Copies a screenshot into `dir`, returning the new path.
+pub fn copy_screenshot(shot: &Screenshot, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let name = shot
+        .path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid screenshot path"))?;
+    let target = dir.as_ref().join(name);
+    atomic::copy(&shot.path, &target)?;
+    Ok(target)
+}
+
+/// Deletes a screenshot and its Steam thumbnail.
+pub fn delete_screenshot(shot: &Screenshot) -> io::Result<()> {
+    guard(WriteOp::Screenshot)?;
+    fs::remove_file(&shot.path)?;
+    if let Some(thumbnail) = &shot.thumbnail {
+        fs::remove_file(thumbnail).ok();
+    }
+    Ok(())
+}
@@ -0,0 +1,351 @@
+//! # sessions
+//!
+//! Play sessions recorded by watching the running processes. A game counts as
+//! running while a process runs an executable inside its install folder, which
+//! also catches games started outside of Steam. Wine runs every game from its
+//! own binary, for Wine and Proton processes the working directory and the
+//! program Wine was asked to run are checked instead.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::userdata::Shortcut;
+use crate::{Game, atomic};
+
+/// How often the history is saved while a game runs, so a crash loses at
+/// most this much playtime. Starts and exits are saved right away.
+const CHECKPOINT: u64 = 5 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub appid: u32,
+    pub start: u64,
+    /// None while the game still runs.
+    pub end: Option<u64>,
+    /// Last time the game was seen running, used to close sessions which were
+    /// still open when Steamtools exited.
+    pub last_seen: u64,
+}
+
+impl Session {
+    pub fn duration(&self) -> u64 {
+        self.end
+            .unwrap_or(self.last_seen)
+            .saturating_sub(self.start)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Turns a path as seen by Wine (`Z:\home\...`) into a unix path.
+#[cfg(target_os = "linux")]
+fn unix_path(arg: &str) -> PathBuf {
+    let arg = arg.replace('\\', "/");
+    match arg.strip_prefix("Z:").or_else(|| arg.strip_prefix("z:")) {
+        Some(rest) => PathBuf::from(rest),
+        None => PathBuf::from(arg),
+    }
+}
+
+/// Paths a process runs from, see the [module docs](self).
+#[cfg(target_os = "linux")]
+fn process_paths(proc: &Path) -> Vec<PathBuf> {
+    let Ok(exe) = fs::read_link(proc.join("exe")) else {
+        return Vec::new();
+    };
+    let wine = exe
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with("wine"));
+    if !wine {
+        return vec![exe];
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_link(proc.join("cwd")).into_iter().collect();
+    // Wine shows the program it runs as the first argument
+    if let Ok(cmdline) = fs::read(proc.join("cmdline"))
+        && let Some(program) = cmdline.split(|b| *b == 0).next()
+        && !program.is_empty()
+    {
+        paths.push(unix_path(&String::from_utf8_lossy(program)));
+    }
+    paths
+}
+
+/// Returns the appids whose path has a running process, a path is either a
+/// folder or a single executable.
+#[cfg(target_os = "linux")]
+pub(crate) fn running(targets: Vec<(u32, PathBuf)>) -> HashSet<u32> {
+    let targets: Vec<(u32, PathBuf)> = targets
+        .into_iter()
+        .map(|(appid, path)| (appid, path.canonicalize().unwrap_or(path)))
+        .collect();
+
+    let mut running = HashSet::new();
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return running;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        if entry
+            .file_name()
+            .to_str()
+            .is_none_or(|n| n.parse::<u32>().is_err())
+        {
+            continue;
+        }
+
+        let paths = process_paths(&entry.path());
+        for (appid, target) in &targets {
+            if paths.iter().any(|p| p.starts_with(target)) {
+                running.insert(*appid);
+            }
+        }
+    }
+    running
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn running(_targets: Vec<(u32, PathBuf)>) -> HashSet<u32> {
+    HashSet::new()
+}
+
+/// Returns the installed games with a running process, only supported on Linux.
+pub fn running_games(games: &HashMap<u32, Game>) -> HashSet<u32> {
+    running(
+        games
+            .values()
+            .filter(|g| g.installed && !g.path.is_empty())
+            .map(|g| (g.appid, PathBuf::from(&g.path)))
+            .collect(),
+    )
+}
+
+/// Returns the non-Steam games that run, only supported on Linux. Their
+/// folder can be anything, so only the executable itself counts.
+pub fn running_shortcuts(shortcuts: &[Shortcut]) -> HashSet<u32> {
+    running(
+        shortcuts
+            .iter()
+            .filter(|s| !s.exe.as_os_str().is_empty())
+            .map(|s| (s.appid, s.exe.clone()))
+            .collect(),
+    )
+}
+
+/// The session history, see [`SessionTracker::update`].
+#[derive(Debug, Default)]
+pub struct SessionTracker {
+    path: PathBuf,
+    sessions: Vec<Session>,
+    /// When the history was last written.
+    saved: u64,
+}
+
+impl SessionTracker {
+    /// Loads the history, sessions left open by a previous run are closed at
+    /// the time they were last seen. A damaged history is kept as
+    /// `<name>.corrupt` and a new one is started.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut sessions: Vec<Session> = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).or_else(|e| {
+                let mut corrupt = path.clone().into_os_string();
+                corrupt.push(".corrupt");
+                warn!("{} is damaged ({e}), moving it aside", path.display());
+                fs::rename(&path, corrupt).map(|_| Vec::new())
+            })?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        for session in sessions.iter_mut().filter(|s| s.end.is_none()) {
+            session.end = Some(session.last_seen);
+        }
+        Ok(Self {
+            path,
+            sessions,
+            saved: now(),
+        })
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        let json = serde_json::to_string(&self.sessions)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        atomic::write(&self.path, json)?;
+        self.saved = now();
+        Ok(())
+    }
+
+    /// Opens sessions for games which started and closes the ones of games
+    /// which exited. Saves the history when that happened and every
+    /// [`CHECKPOINT`] while something runs.
+    pub fn update(&mut self, running: &HashSet<u32>) {
+        let now = now();
+        let mut changed = false;
+
+        for session in self.sessions.iter_mut().filter(|s| s.end.is_none()) {
+            if running.contains(&session.appid) {
+                session.last_seen = now;
+            } else {
+                info!("Game {} exited", session.appid);
+                session.end = Some(now);
+                changed = true;
+            }
+        }
+
+        for appid in running {
+            if !self.is_running(*appid) {
+                info!("Game {appid} started");
+                self.sessions.push(Session {
+                    appid: *appid,
+                    start: now,
+                    end: None,
+                    last_seen: now,
+                });
+                changed = true;
+            }
+        }
+
+        let checkpoint = !running.is_empty() && now.saturating_sub(self.saved) >= CHECKPOINT;
+        if (changed || checkpoint)
+            && let Err(e) = self.save()
+        {
+            warn!("Saving sessions to {}: {e}", self.path.display());
+        }
+    }
+
+    pub fn is_running(&self, appid: u32) -> bool {
+        self.sessions
+            .iter()
+            .any(|s| s.appid == appid && s.end.is_none())
+    }
+
+    pub fn running(&self) -> HashSet<u32> {
+        self.sessions
+            .iter()
+            .filter(|s| s.end.is_none())
+            .map(|s| s.appid)
+            .collect()
+    }
+
+    /// Sessions of a game, newest first.
+    pub fn sessions(&self, appid: u32) -> Vec<Session> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .iter()
+            .filter(|s| s.appid == appid)
+            .copied()
+            .collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.start));
+        sessions
+    }
+
+    /// Total recorded playtime of a game in seconds.
+    pub fn playtime(&self, appid: u32) -> u64 {
+        self.sessions
+            .iter()
+            .filter(|s| s.appid == appid)
+            .map(Session::duration)
+            .sum()
+    }
+}
+
+/// Formats a duration in seconds, e.g. `2h 5m`.
+pub fn format_duration(secs: u64) -> String {
+    match (secs / 3600, (secs % 3600) / 60) {
+        (0, 0) => format!("{secs}s"),
+        (0, m) => format!("{m}m"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn open_and_close_sessions() {
+        let dir = TempDir::new("sessions");
+        let path = dir.join("sessions.json");
+        let mut tracker = SessionTracker::load(&path).unwrap();
+
+        tracker.update(&HashSet::from([10]));
+        assert!(tracker.is_running(10));
+        tracker.update(&HashSet::from([10, 20]));
+        assert_eq!(tracker.running(), HashSet::from([10, 20]));
+        tracker.update(&HashSet::from([20]));
+        assert!(!tracker.is_running(10));
+        assert_eq!(tracker.sessions(10).len(), 1);
+
+        let loaded = SessionTracker::load(&path).unwrap();
+        assert!(loaded.running().is_empty());
+        assert_eq!(loaded.sessions(20).len(), 1);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn finds_running_game() {
+        let library = TempDir::new("running");
+        let dir = library.join("steamapps").join("common").join("Some Game");
+        fs::create_dir_all(&dir).unwrap();
+        // installdir differs from the display name
+        fs::write(
+            library.join("steamapps").join("appmanifest_10.acf"),
+            "\"AppState\"\n{\n\t\"name\"\t\t\"Some: Game\"\n\t\"installdir\"\t\t\"Some Game\"\n}\n",
+        )
+        .unwrap();
+
+        let game = Game {
+            appid: 10,
+            installed: true,
+            path: crate::library::game_dir(library.path(), 10)
+                .unwrap()
+                .to_string_lossy()
+                .to_string(),
+            ..Default::default()
+        };
+        let games = HashMap::from([(10, game)]);
+
+        // A shell or file manager in the folder is not the game
+        let mut shell = std::process::Command::new("sleep")
+            .arg("5")
+            .current_dir(&dir)
+            .spawn()
+            .unwrap();
+        let exe = dir.join("game");
+        fs::copy("/bin/sleep", &exe).unwrap();
+        let before = running_games(&games);
+        let mut child = std::process::Command::new(&exe).arg("5").spawn().unwrap();
+        let running = running_games(&games);
+        for child in [&mut shell, &mut child] {
+            child.kill().ok();
+            child.wait().ok();
+        }
+        assert!(before.is_empty());
+        assert_eq!(running, HashSet::from([10]));
+    }
+
+    #[test]
+    fn keeps_corrupt_history() {
+        let dir = TempDir::new("corrupt");
+        let path = dir.join("sessions.json");
+        fs::write(&path, "[{").unwrap();
+        let tracker = SessionTracker::load(&path).unwrap();
+        assert!(tracker.running().is_empty());
+
+        let mut corrupt = path.clone().into_os_string();
+        corrupt.push(".corrupt");
+        assert_eq!(fs::read_to_string(&corrupt).unwrap(), "[{");
+    }
+}
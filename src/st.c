@@ -2,11 +2,29 @@
 #include <lua.h>
 #include <lualib.h>
 #include <lauxlib.h>
+#ifdef ST_LUAJIT
+#include <luajit.h>
+#endif
 
 #include <stdio.h>
 #include <math.h>
 #include <stdlib.h>
 
+// LuaJIT and Lua 5.1/5.2 lack some of the 5.4 API used below
+#if LUA_VERSION_NUM < 502
+#define LUA_OK 0
+static const char* luaL_tolstring(lua_State* L, int idx, size_t* len) {
+    idx = idx < 0 ? lua_gettop(L) + idx + 1 : idx;
+    lua_getglobal(L, "tostring");
+    lua_pushvalue(L, idx);
+    lua_call(L, 1, 1);
+    return lua_tolstring(L, -1, len);
+}
+#endif
+#if LUA_VERSION_NUM < 503
+#define lua_isinteger(L, idx) lua_isnumber(L, idx)
+#endif
+
 /// @brief Lua state
 typedef struct {
     lua_State* state;
@@ -17,6 +35,66 @@ typedef struct {
 /// @return 
 extern int download(lua_State* L);
 
+/// @brief Collects the output of launch hooks (FFI Rust)
+/// @param s Text, not null terminated
+/// @param len Length of the text
+extern void hook_output(const char* s, size_t len);
+
+/// @brief True once the launch running the hook was cancelled (FFI Rust)
+/// @return 1 if cancelled
+extern int hook_cancelled(void);
+
+/// @brief The linked Lua runtime and what it supports (FFI Rust)
+typedef struct {
+    const char* name;
+    int version;
+    int jit;
+    int integers;
+    int utf8;
+    int bitops;
+    int ffi;
+} Engine;
+
+void lua_engine(Engine* engine) {
+#ifdef LUAJIT_VERSION
+    engine->name = LUAJIT_VERSION;
+    engine->jit = 1;
+    engine->ffi = 1;
+    engine->bitops = 0;
+#else
+    engine->name = LUA_RELEASE;
+    engine->jit = 0;
+    engine->ffi = 0;
+    engine->bitops = LUA_VERSION_NUM >= 503;
+#endif
+    engine->version = LUA_VERSION_NUM;
+    engine->integers = LUA_VERSION_NUM >= 503;
+    engine->utf8 = LUA_VERSION_NUM >= 503;
+}
+
+/// @brief Sets the global table engine, plugins check it before using
+/// features not every runtime has
+static void push_engine(lua_State* L) {
+    Engine engine;
+    lua_engine(&engine);
+    lua_newtable(L);
+    lua_pushstring(L, engine.name);
+    lua_setfield(L, -2, "name");
+    lua_pushinteger(L, engine.version);
+    lua_setfield(L, -2, "version");
+    lua_pushboolean(L, engine.jit);
+    lua_setfield(L, -2, "jit");
+    lua_pushboolean(L, engine.integers);
+    lua_setfield(L, -2, "integers");
+    lua_pushboolean(L, engine.utf8);
+    lua_setfield(L, -2, "utf8");
+    lua_pushboolean(L, engine.bitops);
+    lua_setfield(L, -2, "bitops");
+    lua_pushboolean(L, engine.ffi);
+    lua_setfield(L, -2, "ffi");
+    lua_setglobal(L, "engine");
+}
+
 static int stop_flag = 0;
 
 void set_flag(int val) {
@@ -33,9 +111,17 @@ void hook(lua_State *L, lua_Debug *ar) {
     }
 }
 
+/// @brief Like hook, but with the cancel flag of the launch instead of the plugins' one
+void launch_hook(lua_State *L, lua_Debug *ar) {
+    if (hook_cancelled()) {
+        luaL_error(L, "Launch cancelled by user");
+    }
+}
+
 int run_lua_file(char* filename) {
     lua_State* L = luaL_newstate();
     luaL_openlibs(L);
+    push_engine(L);
     lua_pushcfunction(L, download);
     lua_setglobal(L, "download");
     lua_sethook(L, hook, LUA_MASKCOUNT, 1000);
@@ -50,6 +136,59 @@ int run_lua_file(char* filename) {
     return 0;
 }
 
+/// @brief print replacement for launch hooks, writes to hook_output
+static int hook_print(lua_State* L) {
+    int n = lua_gettop(L);
+    for (int i = 1; i <= n; i++) {
+        size_t len;
+        const char* s = luaL_tolstring(L, i, &len);
+        if (i > 1) {
+            hook_output("\t", 1);
+        }
+        hook_output(s, len);
+        lua_pop(L, 1);
+    }
+    hook_output("\n", 1);
+    return 0;
+}
+
+/// @brief Runs a launch hook with string globals, output goes to hook_output
+/// @param filename Lua file
+/// @param names Names of the globals
+/// @param values Values of the globals
+/// @param count Number of globals
+/// @param code Set to the integer the script returned, 0 if it returned none
+/// @return 0 on success, the lua error status otherwise
+int run_hook_file(const char* filename, const char** names, const char** values, int count, int* code) {
+    lua_State* L = luaL_newstate();
+    luaL_openlibs(L);
+    push_engine(L);
+    lua_pushcfunction(L, download);
+    lua_setglobal(L, "download");
+    lua_pushcfunction(L, hook_print);
+    lua_setglobal(L, "print");
+    for (int i = 0; i < count; i++) {
+        lua_pushstring(L, values[i]);
+        lua_setglobal(L, names[i]);
+    }
+    lua_sethook(L, launch_hook, LUA_MASKCOUNT, 1000);
+
+    *code = 0;
+    int status = luaL_dofile(L, filename);
+    if (status == LUA_OK && lua_gettop(L) > 0 && lua_isinteger(L, -1)) {
+        *code = (int)lua_tointeger(L, -1);
+    } else if (status) {
+        // The error can be any value, error({}) has no string
+        size_t len;
+        const char* err = luaL_tolstring(L, -1, &len);
+        hook_output(err, len);
+        hook_output("\n", 1);
+    }
+
+    lua_close(L);
+    return status;
+}
+
 void load_lua_file(char* filename) {
     int status, result, i;
     lua_State * L = luaL_newstate();
@@ -1,6 +1,9 @@
 #![allow(unused)]
 
-use std::ffi::{CString, c_int};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 mod ffi {
     use core::ffi::c_char;
     use std::ffi::c_int;
@@ -10,33 +13,35 @@ mod ffi {
         _private: [u8; 0],
     }
 
-    #[link(name = "lua", kind = "static")]
+    // build.rs links the vendored, system or LuaJIT library
+    // Only functions every runtime exports, most of the API are macros in
+    // some versions
     unsafe extern "C" {
-        pub fn luaL_newstate() -> *mut LuaState;
-        pub fn luaL_openlibs(L: *mut LuaState);
-        pub fn luaL_dostring(L: *mut LuaState, s: *const c_char) -> c_int;
-        pub fn lua_close(L: *mut LuaState);
-
         pub fn lua_tolstring(L: *mut LuaState, index: c_int, len: *const usize) -> *const c_char;
         pub fn lua_pushboolean(L: *mut LuaState, b: c_int);
-        pub fn lua_getglobal(L: *mut LuaState, name: *const c_char) -> c_int;
-        pub fn lua_pcallk(
-            L: *mut LuaState,
-            args: c_int,
-            nresults: c_int,
-            errfunc: c_int,
-            ctx: c_int,
-            k: *const c_int,
-        ) -> c_int;
-        pub fn luaL_loadfilex(
-            L: *mut LuaState,
-            filename: *const c_char,
-            mode: *const c_char,
-        ) -> c_int;
+    }
+
+    #[repr(C)]
+    pub struct Engine {
+        pub name: *const c_char,
+        pub version: c_int,
+        pub jit: c_int,
+        pub integers: c_int,
+        pub utf8: c_int,
+        pub bitops: c_int,
+        pub ffi: c_int,
     }
 
     unsafe extern "C" {
+        pub(crate) fn lua_engine(engine: *mut Engine);
         pub(crate) fn run_lua_file(filename: *const c_char) -> c_int;
+        pub(crate) fn run_hook_file(
+            filename: *const c_char,
+            names: *const *const c_char,
+            values: *const *const c_char,
+            count: c_int,
+            code: *mut c_int,
+        ) -> c_int;
         pub(crate) fn set_flag(val: c_int);
         pub(crate) fn get_flag() -> c_int;
     }
@@ -54,6 +59,52 @@ pub fn start_file() {
     };
 }
 
+/// The Lua runtime Steamtools was built with, chosen by the `lua-system` and
+/// `luajit` features. Scripts see the same values in the global `engine`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Engine {
+    /// `Lua 5.4.7` or `LuaJIT 2.1.1720049189`
+    pub name: String,
+    /// `LUA_VERSION_NUM`, 504 for Lua 5.4 and 501 for LuaJIT
+    pub version: u32,
+    pub jit: bool,
+    /// Has a separate integer type
+    pub integers: bool,
+    /// Has the `utf8` library
+    pub utf8: bool,
+    /// Has the bitwise operators `&`, `|`, `~`, `<<` and `>>`
+    pub bitops: bool,
+    /// Has LuaJIT's `ffi` library
+    pub ffi: bool,
+}
+
+pub fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut raw = ffi::Engine {
+            name: std::ptr::null(),
+            version: 0,
+            jit: 0,
+            integers: 0,
+            utf8: 0,
+            bitops: 0,
+            ffi: 0,
+        };
+        unsafe { ffi::lua_engine(&mut raw) };
+        Engine {
+            name: unsafe { CStr::from_ptr(raw.name) }
+                .to_string_lossy()
+                .into_owned(),
+            version: raw.version as u32,
+            jit: raw.jit != 0,
+            integers: raw.integers != 0,
+            utf8: raw.utf8 != 0,
+            bitops: raw.bitops != 0,
+            ffi: raw.ffi != 0,
+        }
+    })
+}
+
 pub fn run_lua_file<T: Into<Vec<u8>>>(filename: T) -> Option<()> {
     let s = CString::new(filename).unwrap();
     if unsafe { ffi::run_lua_file(s.as_ptr()) } != 0 {
@@ -62,8 +113,71 @@ pub fn run_lua_file<T: Into<Vec<u8>>>(filename: T) -> Option<()> {
         Some(())
     }
 }
+thread_local! {
+    static HOOK_OUTPUT: RefCell<String> = const { RefCell::new(String::new()) };
+    static HOOK_CANCEL: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Runs a launch hook, the globals are set as strings before the file runs.
+/// Returns the exit code and everything the script printed. The exit code is
+/// the integer the script returned or the Lua error status if it failed.
+///
+/// Setting `cancel` stops the script with an error, plugins have their own
+/// flag, see [`stop_file`].
+pub fn run_hook_file<T: Into<Vec<u8>>>(
+    filename: T,
+    globals: &[(&str, String)],
+    cancel: &Arc<AtomicBool>,
+) -> (i32, String) {
+    let Ok(filename) = CString::new(filename) else {
+        return (-1, "Invalid file name".to_string());
+    };
+    let names: Vec<CString> = globals
+        .iter()
+        .map(|(name, _)| CString::new(*name).unwrap_or_default())
+        .collect();
+    let values: Vec<CString> = globals
+        .iter()
+        .map(|(_, value)| CString::new(value.as_str()).unwrap_or_default())
+        .collect();
+    let name_ptrs: Vec<*const c_char> = names.iter().map(|n| n.as_ptr()).collect();
+    let value_ptrs: Vec<*const c_char> = values.iter().map(|v| v.as_ptr()).collect();
+
+    HOOK_OUTPUT.with_borrow_mut(String::clear);
+    HOOK_CANCEL.set(Some(cancel.clone()));
+    let mut code = 0;
+    let status = unsafe {
+        ffi::run_hook_file(
+            filename.as_ptr(),
+            name_ptrs.as_ptr(),
+            value_ptrs.as_ptr(),
+            globals.len() as c_int,
+            &mut code,
+        )
+    };
+    let output = HOOK_OUTPUT.with_borrow_mut(std::mem::take);
+    HOOK_CANCEL.set(None);
+
+    (if status == 0 { code } else { status }, output)
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn hook_cancelled() -> c_int {
+    HOOK_CANCEL.with_borrow(|cancel| cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed)))
+        as c_int
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn hook_output(s: *const c_char, len: usize) {
+    if s.is_null() {
+        return;
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(s.cast::<u8>(), len) };
+    HOOK_OUTPUT.with_borrow_mut(|out| out.push_str(&String::from_utf8_lossy(bytes)));
+}
+
 mod macros {
-    use crate::st::ffi::{LuaState, lua_pcallk, luaL_loadfilex};
+    use crate::st::ffi::LuaState;
     use std::{
         ffi::{c_char, c_int},
         ptr::null,
@@ -72,21 +186,15 @@ mod macros {
     pub fn lua_tostring(l: *mut LuaState, index: c_int) -> *const c_char {
         unsafe { super::ffi::lua_tolstring(l, index, null()) }
     }
-
-    pub fn lua_pcall(l: *mut LuaState, args: c_int, nresults: c_int, errfunc: c_int) -> c_int {
-        unsafe { lua_pcallk(l, args, nresults, errfunc, 0, null()) }
-    }
-
-    pub fn lua_loadfile(l: *mut LuaState, filename: *const c_char) -> c_int {
-        unsafe { luaL_loadfilex(l, filename, null()) }
-    }
 }
 
 #[unsafe(no_mangle)]
 unsafe extern "C" fn download(l: *mut ffi::LuaState) -> c_int {
+    #[cfg(feature = "net")]
+    use crate::atomic;
     use ffi::lua_pushboolean;
     use macros::lua_tostring;
-    use std::{ffi::CStr, fs::File, io::Write};
+    use std::ffi::CStr;
 
     let arg_url = lua_tostring(l, 1);
     if arg_url.is_null() {
@@ -115,11 +223,15 @@ unsafe extern "C" fn download(l: *mut ffi::LuaState) -> c_int {
         }
     };
 
+    // Plugins can't download without the net feature
+    #[cfg(not(feature = "net"))]
+    let _ = (url, out_path);
+    #[cfg(feature = "net")]
     match reqwest::blocking::get(url) {
         Ok(resp) => {
-            if let Ok(bytes) = resp.bytes() {
-                let mut file = File::create(out_path).unwrap();
-                file.write_all(&bytes).unwrap();
+            if let Ok(bytes) = resp.bytes()
+                && atomic::write(out_path, &bytes).is_ok()
+            {
                 unsafe { lua_pushboolean(l, 1) };
                 return 1;
             }
@@ -139,39 +251,71 @@ macro_rules! cstr {
 
 #[cfg(test)]
 mod tests {
-    use std::{
-        ffi::{CStr, CString},
-        fs,
-        io::Write,
-    };
+    use std::{ffi::CString, fs};
 
-    use crate::st::{
-        ffi::{lua_close, lua_getglobal, luaL_newstate, luaL_openlibs, run_lua_file},
-        macros::{lua_loadfile, lua_pcall, lua_tostring},
-    };
+    use crate::st::ffi::run_lua_file;
+    use crate::test_util::TempDir;
+
+    /// Writes a script into a folder that lives as long as the guard.
+    fn script(source: &str) -> (TempDir, String) {
+        let dir = TempDir::new("lua");
+        let path = dir.join("test.lua");
+        fs::write(&path, source).unwrap();
+        (dir, path.to_string_lossy().to_string())
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "lua-system", feature = "luajit")))]
+    fn vendored_engine() {
+        let engine = crate::st::engine();
+        assert_eq!(engine.version, 504);
+        assert!(engine.name.starts_with("Lua 5.4"));
+        assert!(engine.integers && engine.utf8 && !engine.jit);
+
+        let (_dir, file) = script("print(engine.name)\nreturn engine.version");
+        let (code, output) = crate::st::run_hook_file(file.as_str(), &[], &Default::default());
+        assert_eq!(code, 504);
+        assert_eq!(output.trim(), engine.name);
+    }
+
+    #[test]
+    fn hook() {
+        let (_dir, file) = script("print(appid, phase)\nreturn 3");
+        let (code, output) = crate::st::run_hook_file(
+            file.as_str(),
+            &[("appid", "70".to_string()), ("phase", "pre".to_string())],
+            &Default::default(),
+        );
+        assert_eq!(code, 3);
+        assert_eq!(output, "70\tpre\n");
+    }
+
+    #[test]
+    fn hook_error_value() {
+        let (_dir, file) = script("error({})");
+        let (code, output) = crate::st::run_hook_file(file.as_str(), &[], &Default::default());
+        assert_ne!(code, 0);
+        assert!(output.starts_with("table: "));
+    }
+
+    #[test]
+    fn hook_cancel() {
+        let (_dir, file) = script("while true do end");
+        let cancel = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let stop = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        });
+        let (code, output) = crate::st::run_hook_file(file.as_str(), &[], &cancel);
+        assert_ne!(code, 0);
+        assert!(output.contains("Launch cancelled"));
+    }
 
     #[test]
     fn run() {
-        let mut f = fs::File::create("test.lua").unwrap();
-        f.write_all(b"print(\"test\")");
-        f.flush();
-
-        let s = CString::new("test.lua").unwrap();
-        unsafe { run_lua_file(s.as_ptr()) };
-        // let l = unsafe { luaL_newstate() };
-        // let t = CString::new("Ui").unwrap();
-        // unsafe { luaL_openlibs(l) };
-
-        // if unsafe { lua_loadfile(l, s.as_ptr()) } != 0 {
-        //     let err = unsafe { CStr::from_ptr(lua_tostring(l, -1)) };
-        //     eprintln!("Error loading Lua file: {:?}", err);
-        //     return;
-        // }
-
-        // lua_pcall(l, 0, 0, 0);
-        // unsafe { lua_getglobal(l, t.as_ptr()) };
-        // lua_pcall(l, 0, 0, 0);
-
-        // unsafe { lua_close(l) };
+        let (_dir, file) = script("print(\"test\")");
+        let s = CString::new(file).unwrap();
+        assert_eq!(unsafe { run_lua_file(s.as_ptr()) }, 0);
     }
 }
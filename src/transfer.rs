@@ -0,0 +1,381 @@
+//! # transfer
+//!
+//! Moves settings and state between machines as one `.tar.gz` archive.
+//!
+//! The archive holds [`MANIFEST_FILE`] with the stored values (see
+//! [`crate::migrate::State`]) and the files of the plugin and mod folders,
+//! optionally the artwork cache too. Machine specific values like the Steam
+//! path are left out by the caller.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::io::{self, Error, ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::atomic;
+use crate::migrate::State;
+use crate::paths;
+
+pub const MANIFEST_FILE: &str = "steamtools.json";
+/// Largest file taken from an archive.
+const MAX_ENTRY: u64 = 64 * 1024 * 1024;
+
+/// Folders carried in an archive.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Folder {
+    Plugins,
+    Mods,
+    Artwork,
+}
+
+impl Folder {
+    pub const ALL: [Folder; 3] = [Folder::Plugins, Folder::Mods, Folder::Artwork];
+
+    /// Name inside the archive.
+    fn name(&self) -> &'static str {
+        match self {
+            Folder::Plugins => "plugins",
+            Folder::Mods => "mods",
+            Folder::Artwork => "icons",
+        }
+    }
+
+    fn dir(&self) -> PathBuf {
+        match self {
+            Folder::Plugins => paths::plugins_dir(),
+            Folder::Mods => paths::mods_dir(),
+            Folder::Artwork => paths::icons_dir(),
+        }
+    }
+}
+
+impl Display for Folder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Folder::Plugins => write!(f, "Plugin"),
+            Folder::Mods => write!(f, "Mod"),
+            Folder::Artwork => write!(f, "Artwork"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+struct Manifest {
+    /// Steamtools version that wrote the archive.
+    version: String,
+    created: u64,
+    folders: Vec<Folder>,
+    state: State,
+}
+
+/// A read archive, see [`Archive::preview`] and [`Archive::apply`].
+#[derive(Debug, Default)]
+pub struct Archive {
+    pub version: String,
+    pub created: u64,
+    pub state: State,
+    pub folders: Vec<Folder>,
+    /// File names per folder with their contents.
+    pub files: BTreeMap<(Folder, String), Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Adds everything from the archive, overwriting what differs.
+    Merge,
+    /// Like merge, and removes files and values the archive does not have.
+    Replace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Add,
+    Overwrite,
+    Remove,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Add => write!(f, "add"),
+            Action::Overwrite => write!(f, "overwrite"),
+            Action::Remove => write!(f, "remove"),
+        }
+    }
+}
+
+/// One change an import makes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Planned {
+    pub action: Action,
+    /// Folder of a file, None for a stored value.
+    pub folder: Option<Folder>,
+    pub name: String,
+}
+
+impl Display for Planned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.folder {
+            Some(folder) => write!(f, "{} {folder} {}", self.action, self.name),
+            None => write!(f, "{} setting {}", self.action, self.name),
+        }
+    }
+}
+
+fn files_in(dir: &Path) -> BTreeSet<String> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .collect()
+}
+
+/// Writes `state` and the folders to `target`, the artwork cache only if
+/// `artwork` is set.
+pub fn export(target: impl AsRef<Path>, state: &State, artwork: bool) -> io::Result<()> {
+    let folders: Vec<Folder> = Folder::ALL
+        .into_iter()
+        .filter(|f| artwork || *f != Folder::Artwork)
+        .collect();
+    let manifest = Manifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        created: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        folders: folders.clone(),
+        state: state.clone(),
+    };
+    let json =
+        serde_json::to_vec_pretty(&manifest).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    atomic::Writer::new(target.as_ref()).write_with(|file| {
+        let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(json.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created);
+        tar.append_data(&mut header, MANIFEST_FILE, json.as_slice())?;
+
+        for folder in folders {
+            let dir = folder.dir();
+            for name in files_in(&dir) {
+                tar.append_path_with_name(dir.join(&name), Path::new(folder.name()).join(&name))?;
+            }
+        }
+
+        tar.into_inner()?.finish()?;
+        Ok(())
+    })?;
+    info!("Exported settings to {}", target.as_ref().display());
+    Ok(())
+}
+
+/// Splits `folder/name` and rejects anything else, an archive never writes
+/// outside of the known folders.
+fn entry_name(path: &Path) -> Option<(Folder, String)> {
+    let mut components = path.components();
+    let (Some(Component::Normal(dir)), Some(Component::Normal(name)), None) =
+        (components.next(), components.next(), components.next())
+    else {
+        return None;
+    };
+    let folder = Folder::ALL.into_iter().find(|f| f.name() == dir)?;
+    Some((folder, name.to_str()?.to_string()))
+}
+
+impl Archive {
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut tar = tar::Archive::new(GzDecoder::new(File::open(path)?));
+        let mut manifest: Option<Manifest> = None;
+        let mut files = BTreeMap::new();
+
+        for entry in tar.entries()? {
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            if entry.size() > MAX_ENTRY {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} is too large", entry.path()?.display()),
+                ));
+            }
+            let path = entry.path()?.into_owned();
+            let mut data = Vec::with_capacity(entry.size() as usize);
+            entry.take(MAX_ENTRY).read_to_end(&mut data)?;
+
+            if path == Path::new(MANIFEST_FILE) {
+                manifest = Some(
+                    serde_json::from_slice(&data)
+                        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+                );
+            } else if let Some(key) = entry_name(&path) {
+                files.insert(key, data);
+            } else {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unexpected file {} in the archive", path.display()),
+                ));
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Not a Steamtools export, {MANIFEST_FILE} is missing"),
+            )
+        })?;
+        Ok(Self {
+            version: manifest.version,
+            created: manifest.created,
+            state: manifest.state,
+            folders: manifest.folders,
+            files,
+        })
+    }
+
+    /// What [`Archive::apply`] would change, nothing is written.
+    pub fn preview(&self, current: &State, mode: Mode) -> Vec<Planned> {
+        let mut planned = Vec::new();
+
+        for (key, value) in &self.state {
+            let action = match current.get(key) {
+                None => Action::Add,
+                Some(v) if v != value => Action::Overwrite,
+                Some(_) => continue,
+            };
+            planned.push(Planned {
+                action,
+                folder: None,
+                name: key.clone(),
+            });
+        }
+        if mode == Mode::Replace {
+            for key in current.keys().filter(|k| !self.state.contains_key(*k)) {
+                planned.push(Planned {
+                    action: Action::Remove,
+                    folder: None,
+                    name: key.clone(),
+                });
+            }
+        }
+
+        for &folder in &self.folders {
+            let dir = folder.dir();
+            let existing = files_in(&dir);
+            for ((_, name), data) in self.files.iter().filter(|((f, _), _)| *f == folder) {
+                let action = match fs::read(dir.join(name)) {
+                    Err(_) => Action::Add,
+                    Ok(local) if local != *data => Action::Overwrite,
+                    Ok(_) => continue,
+                };
+                planned.push(Planned {
+                    action,
+                    folder: Some(folder),
+                    name: name.clone(),
+                });
+            }
+            if mode == Mode::Replace {
+                for name in existing
+                    .into_iter()
+                    .filter(|n| !self.files.contains_key(&(folder, n.clone())))
+                {
+                    planned.push(Planned {
+                        action: Action::Remove,
+                        folder: Some(folder),
+                        name,
+                    });
+                }
+            }
+        }
+        planned
+    }
+
+    /// Writes the files and returns the new state, which the caller loads.
+    pub fn apply(&self, current: &State, mode: Mode) -> io::Result<State> {
+        let planned = self.preview(current, mode);
+        let mut state = current.clone();
+
+        for change in &planned {
+            let Some(folder) = change.folder else {
+                match change.action {
+                    Action::Remove => state.remove(&change.name),
+                    _ => state.insert(change.name.clone(), self.state[&change.name].clone()),
+                };
+                continue;
+            };
+
+            let path = folder.dir().join(&change.name);
+            match change.action {
+                Action::Remove => fs::remove_file(&path)?,
+                _ => {
+                    fs::create_dir_all(folder.dir())?;
+                    atomic::write(&path, &self.files[&(folder, change.name.clone())])?;
+                }
+            }
+        }
+
+        info!("Imported {} changes", planned.len());
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_names() {
+        assert_eq!(
+            entry_name(Path::new("plugins/hello.lua")),
+            Some((Folder::Plugins, "hello.lua".to_string()))
+        );
+        assert_eq!(entry_name(Path::new("plugins/../../evil.lua")), None);
+        assert_eq!(entry_name(Path::new("/etc/passwd")), None);
+        assert_eq!(entry_name(Path::new("other/file")), None);
+    }
+
+    #[test]
+    fn preview_state() {
+        let archive = Archive {
+            state: State::from([
+                ("settings".to_string(), "{\"a\":true}".to_string()),
+                ("unlock".to_string(), "true".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let current = State::from([
+            ("settings".to_string(), "{\"a\":false}".to_string()),
+            ("changes".to_string(), "[]".to_string()),
+        ]);
+
+        let actions = |mode| -> Vec<(Action, String)> {
+            archive
+                .preview(&current, mode)
+                .into_iter()
+                .map(|p| (p.action, p.name))
+                .collect()
+        };
+        assert_eq!(
+            actions(Mode::Merge),
+            [
+                (Action::Overwrite, "settings".to_string()),
+                (Action::Add, "unlock".to_string())
+            ]
+        );
+        assert_eq!(actions(Mode::Replace).len(), 3);
+
+        let state = archive.apply(&current, Mode::Merge).unwrap();
+        assert_eq!(state["unlock"], "true");
+        assert_eq!(state["changes"], "[]");
+    }
+}
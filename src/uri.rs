@@ -0,0 +1,64 @@
+//! # uri
+//!
+//! `steam://` links handled by the Steam client.
+
+use std::fmt::{self, Display};
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SteamUri {
+    /// Opens the main window, starting Steam if needed.
+    Main,
+    Exit,
+    Run(u32),
+    Install(u32),
+    Uninstall(u32),
+    /// Verifies the integrity of the game files.
+    Validate(u32),
+    /// Steam's own backup wizard.
+    Backup(u32),
+    StorePage(u32),
+    Properties(u32),
+    Screenshots(u32),
+    /// Opens the game in the Steam library.
+    LibraryPage(u32),
+}
+
+impl Display for SteamUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SteamUri::Main => write!(f, "steam://open/main"),
+            SteamUri::Exit => write!(f, "steam://exit"),
+            SteamUri::Run(appid) => write!(f, "steam://rungameid/{appid}"),
+            SteamUri::Install(appid) => write!(f, "steam://install/{appid}"),
+            SteamUri::Uninstall(appid) => write!(f, "steam://uninstall/{appid}"),
+            SteamUri::Validate(appid) => write!(f, "steam://validate/{appid}"),
+            SteamUri::Backup(appid) => write!(f, "steam://backup/{appid}"),
+            SteamUri::StorePage(appid) => write!(f, "steam://store/{appid}"),
+            SteamUri::Properties(appid) => write!(f, "steam://gameproperties/{appid}"),
+            SteamUri::Screenshots(appid) => write!(f, "steam://open/screenshots/{appid}"),
+            SteamUri::LibraryPage(appid) => write!(f, "steam://nav/games/details/{appid}"),
+        }
+    }
+}
+
+impl SteamUri {
+    /// Hands the link to Steam through the system's url handler.
+    pub fn open(&self) -> io::Result<()> {
+        crate::open(self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_uris() {
+        assert_eq!(SteamUri::Validate(70).to_string(), "steam://validate/70");
+        assert_eq!(
+            SteamUri::LibraryPage(440).to_string(),
+            "steam://nav/games/details/440"
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! # userdata
+//!
+//! Helpers for the per account folders in `<steam>/userdata/<accountid>`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::vdf;
+
+/// Offset between a 64 bit SteamID and the 32 bit account id used by `userdata`.
+pub const STEAMID64_BASE: u64 = 76561197960265728;
+
+/// Path of the userdata folder of an account.
+pub fn user_dir(path: impl AsRef<Path>, account: u32) -> PathBuf {
+    path.as_ref().join("userdata").join(account.to_string())
+}
+
+/// Lists all account ids that have a userdata folder.
+pub fn users(path: impl AsRef<Path>) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(path.as_ref().join("userdata")) else {
+        return Vec::new();
+    };
+
+    let mut users: Vec<u32> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().to_str()?.parse::<u32>().ok())
+        // 0 is used by Steam for anonymous / not logged in data
+        .filter(|id| *id != 0)
+        .collect();
+    users.sort_unstable();
+    users
+}
+
+/// Returns the account which logged in last according to `config/loginusers.vdf`,
+/// falling back to the first userdata folder.
+pub fn active_user(path: impl AsRef<Path>) -> Option<u32> {
+    let path = path.as_ref();
+    let most_recent = vdf::read(path.join("config").join("loginusers.vdf"))
+        .ok()
+        .and_then(|doc| {
+            doc.get("users")?
+                .entries()
+                .iter()
+                .find(|(_, user)| user.get_str("MostRecent") == Some("1"))
+                .and_then(|(id, _)| id.parse::<u64>().ok())
+        })
+        .and_then(|id| u32::try_from(id.checked_sub(STEAMID64_BASE)?).ok());
+
+    most_recent.or_else(|| users(path).first().copied())
+}
+
+/// A non-Steam game added to the library, from `config/shortcuts.vdf`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shortcut {
+    /// Generated by Steam, never collides with the appid of a Steam game.
+    pub appid: u32,
+    pub name: String,
+    pub exe: PathBuf,
+}
+
+/// Lists the shortcuts of every account.
+pub fn shortcuts(path: impl AsRef<Path>) -> Vec<Shortcut> {
+    let path = path.as_ref();
+    let mut shortcuts = Vec::new();
+
+    for account in users(path) {
+        let Ok(data) = fs::read(user_dir(path, account).join("config").join("shortcuts.vdf"))
+        else {
+            continue;
+        };
+        let Some(list) = vdf::parse_binary(&data)
+            .ok()
+            .and_then(|doc| doc.get("shortcuts").cloned())
+        else {
+            continue;
+        };
+
+        shortcuts.extend(list.entries().iter().filter_map(|(_, shortcut)| {
+            Some(Shortcut {
+                appid: shortcut.get_str("appid")?.parse().ok()?,
+                name: shortcut.get_str("AppName").unwrap_or_default().to_string(),
+                // Quoted by Steam
+                exe: PathBuf::from(shortcut.get_str("Exe")?.trim_matches('"')),
+            })
+        }));
+    }
+    shortcuts
+}
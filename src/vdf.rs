@@ -0,0 +1,758 @@
+//! # vdf
+//!
+//! Minimal reader and writer for Valve's text KeyValues format (`.vdf` / `.acf`),
+//! and a reader for the binary one of `shortcuts.vdf`, see [`parse_binary`].
+//!
+//! [`Vdf`] is a plain tree for reading, [`Document`] edits a file in place and
+//! keeps its formatting, comments and unknown keys.
+
+use std::fs;
+use std::io::{self, Error, ErrorKind};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::atomic;
+
+/// A KeyValues node, either a string or an ordered list of children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Vdf {
+    Str(String),
+    Obj(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    /// Returns the first child with the given key (case insensitive, like Steam).
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Obj(children) => children
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Vdf::Str(_) => None,
+        }
+    }
+
+    /// Follows a path of keys, e.g. `["Software", "Valve", "Steam"]`.
+    pub fn path(&self, keys: &[&str]) -> Option<&Vdf> {
+        keys.iter().try_fold(self, |node, key| node.get(key))
+    }
+
+    /// Shortcut for `get(key)` followed by `as_str`.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Vdf::as_str)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Vdf::Str(s) => Some(s),
+            Vdf::Obj(_) => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Vdf> {
+        match self {
+            Vdf::Obj(children) => children
+                .iter_mut()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Vdf::Str(_) => None,
+        }
+    }
+
+    /// Returns the child object with the given key, creating it (or replacing
+    /// a string with the same key) if needed. Does nothing useful on strings.
+    pub fn obj_mut(&mut self, key: &str) -> &mut Vdf {
+        if !matches!(self.get(key), Some(Vdf::Obj(_))) {
+            self.set(key, Vdf::Obj(Vec::new()));
+        }
+        self.get_mut(key).unwrap()
+    }
+
+    /// Replaces the value of `key` or appends it if it doesn't exist yet.
+    pub fn set(&mut self, key: &str, value: Vdf) {
+        if let Some(v) = self.get_mut(key) {
+            *v = value;
+        } else if let Vdf::Obj(children) = self {
+            children.push((key.to_string(), value));
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Vdf> {
+        match self {
+            Vdf::Obj(children) => {
+                let i = children
+                    .iter()
+                    .position(|(k, _)| k.eq_ignore_ascii_case(key))?;
+                Some(children.remove(i).1)
+            }
+            Vdf::Str(_) => None,
+        }
+    }
+
+    /// Children of an object, empty for strings.
+    pub fn entries(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Obj(children) => children,
+            Vdf::Str(_) => &[],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = &self.src[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if trimmed.starts_with('[') {
+                // Platform conditionals like [$WIN32] are ignored
+                self.pos += trimmed.find(']').map_or(trimmed.len(), |i| i + 1);
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Returns the next token and its byte range in the source.
+    fn next(&mut self) -> io::Result<Option<(Token, Range<usize>)>> {
+        self.skip_trivia();
+        let start = self.pos;
+        Ok(self.token()?.map(|t| (t, start..self.pos)))
+    }
+
+    fn token(&mut self) -> io::Result<Option<Token>> {
+        let rest = &self.src[self.pos..];
+        let Some(c) = rest.chars().next() else {
+            return Ok(None);
+        };
+
+        match c {
+            '{' => {
+                self.pos += 1;
+                Ok(Some(Token::Open))
+            }
+            '}' => {
+                self.pos += 1;
+                Ok(Some(Token::Close))
+            }
+            '"' => {
+                let mut out = String::new();
+                let mut chars = rest.char_indices().skip(1);
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            self.pos += i + 1;
+                            return Ok(Some(Token::Str(out)));
+                        }
+                        '\\' => match chars.next() {
+                            Some((_, 'n')) => out.push('\n'),
+                            Some((_, 't')) => out.push('\t'),
+                            Some((_, c)) => out.push(c),
+                            None => break,
+                        },
+                        c => out.push(c),
+                    }
+                }
+                Err(Error::new(ErrorKind::InvalidData, "Unterminated string"))
+            }
+            _ => {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | '"'))
+                    .unwrap_or(rest.len());
+                self.pos += len;
+                Ok(Some(Token::Str(rest[..len].to_string())))
+            }
+        }
+    }
+}
+
+/// A parsed key with the byte ranges of its tokens, used by [`Document`].
+struct Node {
+    key: String,
+    /// From the start of the key to the end of the value, `None` for keys
+    /// added by an [`Edit`].
+    span: Option<Range<usize>>,
+    value: NodeValue,
+}
+
+enum NodeValue {
+    Str(String, Range<usize>),
+    /// Children and the position of the closing brace.
+    Obj(Vec<Node>, usize),
+}
+
+fn parse_nodes(lexer: &mut Lexer, nested: bool) -> io::Result<Vec<Node>> {
+    let mut children = Vec::new();
+    loop {
+        let (key, start) = match lexer.next()? {
+            Some((Token::Str(key), span)) => (key, span.start),
+            Some((Token::Close, _)) if nested => return Ok(children),
+            None if !nested => return Ok(children),
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Expected key")),
+        };
+
+        let value = match lexer.next()? {
+            Some((Token::Str(s), span)) => NodeValue::Str(s, span),
+            Some((Token::Open, _)) => {
+                let nodes = parse_nodes(lexer, true)?;
+                NodeValue::Obj(nodes, lexer.pos - 1)
+            }
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Expected value")),
+        };
+
+        children.push(Node {
+            key,
+            span: Some(start..lexer.pos),
+            value,
+        });
+    }
+}
+
+fn into_vdf(nodes: Vec<Node>) -> Vdf {
+    Vdf::Obj(
+        nodes
+            .into_iter()
+            .map(|node| {
+                let value = match node.value {
+                    NodeValue::Str(s, _) => Vdf::Str(s),
+                    NodeValue::Obj(children, _) => into_vdf(children),
+                };
+                (node.key, value)
+            })
+            .collect(),
+    )
+}
+
+/// Parses a KeyValues document. The top level is returned as an object so
+/// files with more than one root key are handled too.
+pub fn parse(text: &str) -> io::Result<Vdf> {
+    let mut lexer = Lexer { src: text, pos: 0 };
+    parse_nodes(&mut lexer, false).map(into_vdf)
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_obj(out: &mut String, children: &[(String, Vdf)], depth: usize) {
+    for (key, value) in children {
+        out.extend(std::iter::repeat_n('\t', depth));
+        write_str(out, key);
+        match value {
+            Vdf::Str(s) => {
+                out.push_str("\t\t");
+                write_str(out, s);
+                out.push('\n');
+            }
+            Vdf::Obj(children) => {
+                out.push('\n');
+                out.extend(std::iter::repeat_n('\t', depth));
+                out.push_str("{\n");
+                write_obj(out, children, depth + 1);
+                out.extend(std::iter::repeat_n('\t', depth));
+                out.push_str("}\n");
+            }
+        }
+    }
+}
+
+/// Serializes a document the way Steam writes its files (tabs, one key per line).
+pub fn to_string(doc: &Vdf) -> String {
+    let mut out = String::new();
+    write_obj(&mut out, doc.entries(), 0);
+    out
+}
+
+/// Writes a document to a file, see [`to_string`].
+pub fn write(path: impl AsRef<Path>, doc: &Vdf) -> io::Result<()> {
+    atomic::write(path, to_string(doc))
+}
+
+/// Reads and parses a KeyValues file.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vdf> {
+    parse(&fs::read_to_string(path)?)
+}
+
+fn binary_take<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> io::Result<&'a [u8]> {
+    let bytes = data
+        .get(*pos..*pos + len)
+        .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Truncated binary vdf"))?;
+    *pos += len;
+    Ok(bytes)
+}
+
+fn binary_str(data: &[u8], pos: &mut usize) -> io::Result<String> {
+    let len = data
+        .get(*pos..)
+        .and_then(|rest| rest.iter().position(|b| *b == 0))
+        .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "Unterminated string"))?;
+    let s = String::from_utf8_lossy(binary_take(data, pos, len)?).to_string();
+    *pos += 1;
+    Ok(s)
+}
+
+fn binary_obj(data: &[u8], pos: &mut usize) -> io::Result<Vec<(String, Vdf)>> {
+    let mut children = Vec::new();
+    // The top level can end without a marker
+    while let Some(&kind) = data.get(*pos) {
+        *pos += 1;
+        if matches!(kind, 0x08 | 0x0b) {
+            break;
+        }
+
+        let key = binary_str(data, pos)?;
+        let value = match kind {
+            0x00 => Vdf::Obj(binary_obj(data, pos)?),
+            0x01 => Vdf::Str(binary_str(data, pos)?),
+            0x02 => {
+                let b = binary_take(data, pos, 4)?;
+                Vdf::Str(u32::from_le_bytes([b[0], b[1], b[2], b[3]]).to_string())
+            }
+            0x03 => {
+                let b = binary_take(data, pos, 4)?;
+                Vdf::Str(f32::from_le_bytes([b[0], b[1], b[2], b[3]]).to_string())
+            }
+            0x07 => {
+                let b = binary_take(data, pos, 8)?;
+                Vdf::Str(u64::from_le_bytes(b.try_into().unwrap()).to_string())
+            }
+            kind => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown binary vdf type {kind}"),
+                ));
+            }
+        };
+        children.push((key, value));
+    }
+    Ok(children)
+}
+
+/// Parses binary KeyValues. Numbers are turned into strings, so the result
+/// reads like a text document.
+pub fn parse_binary(data: &[u8]) -> io::Result<Vdf> {
+    binary_obj(data, &mut 0).map(Vdf::Obj)
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    write_str(&mut out, s);
+    out
+}
+
+/// Renders a key added by an [`Edit`], its children are all new too.
+fn render(node: &Node, indent: &str, out: &mut String) {
+    out.push_str(indent);
+    write_str(out, &node.key);
+    match &node.value {
+        NodeValue::Str(s, _) => {
+            out.push_str("\t\t");
+            write_str(out, s);
+            out.push('\n');
+        }
+        NodeValue::Obj(children, _) => {
+            out.push('\n');
+            out.push_str(indent);
+            out.push_str("{\n");
+            for child in children {
+                render(child, &format!("{indent}\t"), out);
+            }
+            out.push_str(indent);
+            out.push_str("}\n");
+        }
+    }
+}
+
+/// Grows `span` to the whole line if nothing else is on it.
+fn line_span(text: &str, span: Range<usize>) -> Range<usize> {
+    let line_start = text[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    if !text[line_start..span.start].trim().is_empty() {
+        return span;
+    }
+
+    let rest = &text[span.end..];
+    let trailing = rest.len() - rest.trim_start_matches([' ', '\t', '\r']).len();
+    match rest[trailing..].starts_with('\n') {
+        true => line_start..span.end + trailing + 1,
+        false if trailing == rest.len() => line_start..text.len(),
+        false => span,
+    }
+}
+
+/// A KeyValues file that is edited in place. Only the changed values are
+/// touched, everything else (formatting, comments, keys this crate doesn't
+/// know about) is written back exactly as it was read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    text: String,
+}
+
+impl Document {
+    pub fn parse(text: impl Into<String>) -> io::Result<Self> {
+        let text = text.into();
+        parse_nodes(&mut Lexer { src: &text, pos: 0 }, false)?;
+        Ok(Self { text })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(fs::read_to_string(path)?)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        atomic::write(path, &self.text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The document as a plain tree.
+    pub fn to_vdf(&self) -> Vdf {
+        into_vdf(self.nodes())
+    }
+
+    fn nodes(&self) -> Vec<Node> {
+        // The text is validated on creation and every edit keeps it valid
+        parse_nodes(
+            &mut Lexer {
+                src: &self.text,
+                pos: 0,
+            },
+            false,
+        )
+        .expect("Document is always valid")
+    }
+
+    /// Returns the string at `path`.
+    pub fn get(&self, path: &[&str]) -> Option<String> {
+        self.to_vdf().path(path)?.as_str().map(str::to_string)
+    }
+
+    /// Makes several changes at once. The text is parsed and rebuilt once for
+    /// the whole batch instead of once per change.
+    pub fn edit<T>(&mut self, f: impl FnOnce(&mut Edit) -> T) -> T {
+        let mut edit = Edit {
+            text: &self.text,
+            nodes: self.nodes(),
+            changes: Vec::new(),
+        };
+        let out = f(&mut edit);
+        self.text = edit.finish();
+        out
+    }
+
+    /// Sets a single string, see [`Edit::set`].
+    pub fn set(&mut self, path: &[&str], value: &str) {
+        self.edit(|edit| edit.set(path, value));
+    }
+
+    /// Removes a single key, see [`Edit::remove`].
+    pub fn remove(&mut self, path: &[&str]) -> bool {
+        self.edit(|edit| edit.remove(path))
+    }
+}
+
+/// Changes to a [`Document`], see [`Document::edit`]. They are kept as
+/// replaced ranges of the original text and applied together at the end.
+pub struct Edit<'a> {
+    text: &'a str,
+    nodes: Vec<Node>,
+    changes: Vec<(Range<usize>, String)>,
+}
+
+impl Edit<'_> {
+    /// Sets the string at `path`, creating missing objects on the way.
+    pub fn set(&mut self, path: &[&str], value: &str) {
+        let Some((last, parents)) = path.split_last() else {
+            return;
+        };
+
+        let mut children = &mut self.nodes;
+        for key in parents {
+            let node = entry(self.text, &mut self.changes, children, key, true);
+            let NodeValue::Obj(nodes, _) = &mut node.value else {
+                unreachable!("entry returns an object");
+            };
+            children = nodes;
+        }
+
+        let node = entry(self.text, &mut self.changes, children, last, false);
+        if let NodeValue::Str(s, range) = &mut node.value {
+            if node.span.is_some() {
+                self.changes.retain(|(r, _)| r != range);
+                self.changes.push((range.clone(), quote(value)));
+            }
+            *s = value.to_string();
+        }
+    }
+
+    /// Removes the key at `path`, returns false if it didn't exist.
+    pub fn remove(&mut self, path: &[&str]) -> bool {
+        let Some((last, parents)) = path.split_last() else {
+            return false;
+        };
+
+        let mut children = &mut self.nodes;
+        for key in parents {
+            let Some(node) = children
+                .iter_mut()
+                .find(|n| n.key.eq_ignore_ascii_case(key))
+            else {
+                return false;
+            };
+            children = match &mut node.value {
+                NodeValue::Obj(nodes, _) => nodes,
+                NodeValue::Str(..) => return false,
+            };
+        }
+
+        match children
+            .iter()
+            .position(|n| n.key.eq_ignore_ascii_case(last))
+        {
+            Some(i) => {
+                delete(self.text, &mut self.changes, children.remove(i));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        insertions(self.text, &self.nodes, &mut self.changes);
+        self.changes
+            .sort_by_key(|(range, _)| (range.start, range.end));
+
+        let mut out = String::with_capacity(self.text.len());
+        let mut pos = 0;
+        for (range, text) in &self.changes {
+            out.push_str(&self.text[pos..range.start]);
+            out.push_str(text);
+            pos = range.end;
+        }
+        out.push_str(&self.text[pos..]);
+
+        let mut added = String::new();
+        for node in self.nodes.iter().filter(|n| n.span.is_none()) {
+            render(node, "", &mut added);
+        }
+        if !added.is_empty() && !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        out.push_str(&added);
+        out
+    }
+}
+
+/// Returns the key in `children`, replacing it if it holds the wrong kind of
+/// value and adding it if it is missing.
+fn entry<'a>(
+    text: &str,
+    changes: &mut Vec<(Range<usize>, String)>,
+    children: &'a mut Vec<Node>,
+    key: &str,
+    obj: bool,
+) -> &'a mut Node {
+    let found = children
+        .iter()
+        .position(|n| n.key.eq_ignore_ascii_case(key));
+    let i = match found {
+        Some(i) if matches!(children[i].value, NodeValue::Obj(..)) == obj => i,
+        _ => {
+            if let Some(i) = found {
+                delete(text, changes, children.remove(i));
+            }
+            // Positions are only read for keys that were in the text
+            let value = match obj {
+                true => NodeValue::Obj(Vec::new(), 0),
+                false => NodeValue::Str(String::new(), 0..0),
+            };
+            children.push(Node {
+                key: key.to_string(),
+                span: None,
+                value,
+            });
+            children.len() - 1
+        }
+    };
+    &mut children[i]
+}
+
+/// Drops the text of a removed key along with the changes made inside it.
+fn delete(text: &str, changes: &mut Vec<(Range<usize>, String)>, node: Node) {
+    if let Some(span) = node.span {
+        let span = line_span(text, span);
+        changes.retain(|(r, _)| r.start < span.start || r.end > span.end);
+        changes.push((span, String::new()));
+    }
+}
+
+/// Adds the text of the keys added to objects that were already in the text,
+/// before their closing brace.
+fn insertions(text: &str, nodes: &[Node], changes: &mut Vec<(Range<usize>, String)>) {
+    for node in nodes.iter().filter(|n| n.span.is_some()) {
+        let NodeValue::Obj(children, close) = &node.value else {
+            continue;
+        };
+        insertions(text, children, changes);
+
+        let mut added = children.iter().filter(|n| n.span.is_none()).peekable();
+        if added.peek().is_none() {
+            continue;
+        }
+
+        let line_start = text[..*close].rfind('\n').map_or(0, |i| i + 1);
+        let indent = &text[line_start..*close];
+        let mut out = String::new();
+        let at = if indent.trim().is_empty() {
+            let indent = format!("{indent}\t");
+            added.for_each(|n| render(n, &indent, &mut out));
+            line_start
+        } else {
+            // Object written on a single line, e.g. `{ "a" "b" }`
+            out.push('\n');
+            added.for_each(|n| render(n, "\t", &mut out));
+            *close
+        };
+        changes.push((at..at, out));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Document, Vdf, parse, parse_binary, to_string};
+
+    #[test]
+    fn parse_manifest() {
+        let text = r#"
+"AppState"
+{
+	"appid"		"620"
+	"name"		"Portal 2"
+	// comment
+	"UserConfig"
+	{
+		"language"		"english"
+	}
+	"escaped"	"C:\\Games\\\"x\""
+}
+"#;
+        let doc = parse(text).unwrap();
+        let app = doc.get("appstate").unwrap();
+        assert_eq!(app.get_str("appid"), Some("620"));
+        assert_eq!(app.get_str("Name"), Some("Portal 2"));
+        assert_eq!(
+            app.path(&["UserConfig", "language"]).and_then(Vdf::as_str),
+            Some("english")
+        );
+        assert_eq!(app.get_str("escaped"), Some("C:\\Games\\\"x\""));
+    }
+
+    #[test]
+    fn write_roundtrip() {
+        let mut doc = parse("\"root\" { \"a\" \"1\" \"quote\" \"say \\\"hi\\\"\" }").unwrap();
+        let root = doc.get_mut("root").unwrap();
+        root.set("a", Vdf::Str("2".into()));
+        root.obj_mut("new").set("b", Vdf::Str("c\\d".into()));
+
+        let text = to_string(&doc);
+        assert_eq!(parse(&text).unwrap(), doc);
+        assert_eq!(
+            doc.path(&["root", "new", "b"]).and_then(Vdf::as_str),
+            Some("c\\d")
+        );
+    }
+
+    #[test]
+    fn document_keeps_formatting() {
+        let text = "// written by steam\n\"Store\"\n{\n    \"apps\"\n    {\n        \"10\"   {  \"LaunchOptions\" \"-old\" }\n        \"Unknown\" \"kept\"\n    }\n}\n";
+        let mut doc = Document::parse(text).unwrap();
+
+        doc.set(&["Store", "apps", "10", "LaunchOptions"], "-new");
+        doc.set(&["Store", "apps", "20", "LaunchOptions"], "%command% -x");
+        assert_eq!(
+            doc.get(&["store", "apps", "10", "launchoptions"])
+                .as_deref(),
+            Some("-new")
+        );
+        assert_eq!(
+            doc.get(&["Store", "apps", "20", "LaunchOptions"])
+                .as_deref(),
+            Some("%command% -x")
+        );
+        assert!(doc.as_str().starts_with("// written by steam\n"));
+        assert!(doc.as_str().contains("        \"Unknown\" \"kept\"\n"));
+        assert!(doc.as_str().contains(
+            "    \t\"20\"\n    \t{\n    \t\t\"LaunchOptions\"\t\t\"%command% -x\"\n    \t}\n    }"
+        ));
+
+        assert!(doc.remove(&["Store", "apps", "20"]));
+        doc.set(&["Store", "apps", "10", "LaunchOptions"], "-old");
+        assert_eq!(doc.as_str(), text);
+    }
+
+    #[test]
+    fn document_edit_batch() {
+        let text =
+            "\"Store\"\n{\n\t\"apps\"\n\t{\n\t\t\"10\"\t\t\"-a\"\n\t\t\"20\"\t\t\"-b\"\n\t}\n}\n";
+        let mut doc = Document::parse(text).unwrap();
+
+        let removed = doc.edit(|edit| {
+            edit.set(&["Store", "apps", "10"], "-c");
+            edit.set(&["Store", "apps", "30", "name"], "x");
+            edit.set(&["Store", "apps", "30", "priority"], "250");
+            edit.set(&["Store", "apps", "20", "nested"], "y");
+            edit.remove(&["Store", "apps", "10"])
+        });
+        assert!(removed);
+        assert_eq!(
+            doc.as_str(),
+            "\"Store\"\n{\n\t\"apps\"\n\t{\n\t\t\"30\"\n\t\t{\n\t\t\t\"name\"\t\t\"x\"\n\t\t\t\"priority\"\t\t\"250\"\n\t\t}\n\t\t\"20\"\n\t\t{\n\t\t\t\"nested\"\t\t\"y\"\n\t\t}\n\t}\n}\n"
+        );
+        assert!(Document::parse(doc.as_str()).is_ok());
+    }
+
+    #[test]
+    fn reject_unbalanced() {
+        assert!(parse("\"a\" { \"b\" \"c\"").is_err());
+        assert!(parse("\"a\" }").is_err());
+    }
+
+    #[test]
+    fn parse_shortcuts() {
+        let mut data = b"\x00shortcuts\x00\x000\x00\x02appid\x00".to_vec();
+        data.extend_from_slice(&3_000_000_001u32.to_le_bytes());
+        data.extend_from_slice(b"\x01AppName\x00Game\x00\x00tags\x00\x08\x08\x08\x08");
+
+        let doc = parse_binary(&data).unwrap();
+        let shortcut = doc.path(&["shortcuts", "0"]).unwrap();
+        assert_eq!(shortcut.get_str("appid"), Some("3000000001"));
+        assert_eq!(shortcut.get_str("AppName"), Some("Game"));
+        assert!(parse_binary(b"\x01AppName\x00Game").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! # workshop
+//!
+//! Reads the workshop state Steam keeps in
+//! `steamapps/workshop/appworkshop_<appid>.acf` and the downloaded items in
+//! `steamapps/workshop/content/<appid>/<itemid>` of every library folder.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dir_size;
+use crate::library;
+use crate::vdf::{self, Vdf};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkshopItem {
+    pub id: u64,
+    /// Size in bytes, from the manifest or measured on disk.
+    pub size: u64,
+    /// Unix timestamp of the last update.
+    pub updated: u64,
+    /// Account id which subscribed to the item.
+    pub subscribed_by: Option<u32>,
+    pub subscribed: bool,
+    pub installed: bool,
+    /// Content folder, set if it exists on disk.
+    pub path: Option<PathBuf>,
+}
+
+fn num(node: &Vdf, key: &str) -> Option<u64> {
+    node.get_str(key)?.parse().ok()
+}
+
+/// Adds the items of one library folder.
+fn read_library(workshop: &Path, appid: u32, items: &mut BTreeMap<u64, WorkshopItem>) {
+    let content = workshop.join("content").join(appid.to_string());
+    if let Ok(doc) = vdf::read(workshop.join(format!("appworkshop_{appid}.acf"))) {
+        let app = doc.get("AppWorkshop");
+        let section = |key: &str| {
+            app.and_then(|a| a.get(key))
+                .map(Vdf::entries)
+                .unwrap_or_default()
+        };
+
+        for (id, node) in section("WorkshopItemsInstalled") {
+            let Ok(id) = id.parse::<u64>() else { continue };
+            let item = items.entry(id).or_default();
+            item.size = num(node, "size").unwrap_or_default();
+            item.updated = num(node, "timeupdated").unwrap_or_default();
+        }
+
+        for (id, node) in section("WorkshopItemDetails") {
+            let Ok(id) = id.parse::<u64>() else { continue };
+            let item = items.entry(id).or_default();
+            item.subscribed_by = num(node, "subscribedby").map(|s| s as u32);
+            item.subscribed = item.subscribed_by.is_some_and(|s| s != 0);
+            if item.updated == 0 {
+                item.updated = num(node, "timeupdated").unwrap_or_default();
+            }
+        }
+    }
+
+    // Items can be on disk without being listed (e.g. left over after unsubscribing)
+    if let Ok(entries) = fs::read_dir(&content) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            if let Some(id) = entry.file_name().to_str().and_then(|s| s.parse().ok()) {
+                items.entry(id).or_default();
+            }
+        }
+    }
+
+    for (id, item) in items.iter_mut() {
+        let dir = content.join(id.to_string());
+        if item.path.is_none() && dir.is_dir() {
+            item.path = Some(dir);
+        }
+    }
+}
+
+/// Lists subscribed and installed workshop items of a game, sorted by id.
+///
+/// Measures folders the manifest has no size for, so this can take a while.
+pub fn get_workshop_items(path: impl AsRef<Path>, appid: u32) -> Vec<WorkshopItem> {
+    let mut items: BTreeMap<u64, WorkshopItem> = BTreeMap::new();
+    for library in library::library_folders(path) {
+        read_library(
+            &library.join("steamapps").join("workshop"),
+            appid,
+            &mut items,
+        );
+    }
+
+    items
+        .into_iter()
+        .map(|(id, mut item)| {
+            item.id = id;
+            item.installed = item.path.is_some();
+            if let Some(dir) = &item.path
+                && item.size == 0
+            {
+                item.size = dir_size(dir);
+            }
+            item
+        })
+        .collect()
+}